@@ -0,0 +1,144 @@
+//! Timing and reporting shared by the benchmarks.
+//!
+//! Results can be saved as a baseline and compared against later on, e.g. before and after a change to the solver:
+//!
+//! ```text
+//! cargo bench --no-default-features --bench scenarios --bench kernels -- --save baseline.txt
+//! cargo bench --no-default-features --bench scenarios --bench kernels -- --compare baseline.txt
+//! ```
+//!
+//! The benchmarks are named explicitly, since the test harness of the other targets rejects these options.
+//! Any other argument filters the benchmarks by name.
+//! Baselines are plain text with a line `<name> <seconds>` per benchmark,
+//! so that every benchmark binary can merge its results into the same file.
+
+// Not every benchmark binary uses every helper.
+#![allow(dead_code)]
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    time::{Duration, Instant},
+};
+
+/// How long a micro-benchmark is run after warming up.
+const MEASUREMENT_TIME: Duration = Duration::from_secs(1);
+
+/// Micro-benchmarks are timed in batches of at least this duration, which hides the overhead of the clock.
+const BATCH_TIME: Duration = Duration::from_millis(10);
+
+pub struct Bench {
+    filter: Option<String>,
+    save: Option<String>,
+    baseline: BTreeMap<String, f64>,
+    results: BTreeMap<String, f64>,
+}
+
+impl Bench {
+    pub fn from_args() -> Bench {
+        let mut bench = Bench {
+            filter: None,
+            save: None,
+            baseline: BTreeMap::new(),
+            results: BTreeMap::new(),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--save" => bench.save = args.next(),
+                "--compare" => {
+                    let path = args.next().expect("Missing baseline to compare against");
+                    bench.baseline = read(&path)
+                        .unwrap_or_else(|error| panic!("Cannot read baseline {path}: {error}"));
+                }
+                // Passed by `cargo bench`.
+                "--bench" => {}
+                _ if arg.starts_with("--") => {}
+                _ => bench.filter = Some(arg),
+            }
+        }
+        bench
+    }
+
+    /// Whether the benchmark of the given name was selected.
+    pub fn enabled(&self, name: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| name.contains(filter.as_str()))
+    }
+
+    /// Prints a result along with its change relative to the baseline.
+    pub fn report(&mut self, name: &str, seconds: f64) {
+        let change = match self.baseline.get(name) {
+            Some(baseline) => format!("{:+7.1} %", 100.0 * (seconds / baseline - 1.0)),
+            None => String::new(),
+        };
+        println!("{name:<40} {:>12} {change}", format_time(seconds));
+        self.results.insert(name.to_string(), seconds);
+    }
+
+    /// Times a closure over many iterations and reports the median time per iteration.
+    pub fn measure(&mut self, name: &str, mut f: impl FnMut()) {
+        if !self.enabled(name) {
+            return;
+        }
+
+        let mut iterations = 1;
+        loop {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                f();
+            }
+            if start.elapsed() >= BATCH_TIME {
+                break;
+            }
+            iterations *= 2;
+        }
+
+        let mut batches = Vec::new();
+        let start = Instant::now();
+        while start.elapsed() < MEASUREMENT_TIME {
+            let batch = Instant::now();
+            for _ in 0..iterations {
+                f();
+            }
+            batches.push(batch.elapsed().as_secs_f64() / iterations as f64);
+        }
+        batches.sort_by(f64::total_cmp);
+        self.report(name, batches[batches.len() / 2]);
+    }
+
+    /// Merges the results into the baseline file, if one is to be saved.
+    pub fn finish(self) {
+        let Some(path) = self.save else {
+            return;
+        };
+        let mut baseline = read(&path).unwrap_or_default();
+        baseline.extend(self.results);
+        let contents: String = baseline
+            .iter()
+            .map(|(name, seconds)| format!("{name} {seconds:e}\n"))
+            .collect();
+        fs::write(&path, contents)
+            .unwrap_or_else(|error| panic!("Cannot save baseline {path}: {error}"));
+    }
+}
+
+fn read(path: &str) -> std::io::Result<BTreeMap<String, f64>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| {
+            let (name, seconds) = line.rsplit_once(' ')?;
+            Some((name.to_string(), seconds.parse().ok()?))
+        })
+        .collect())
+}
+
+fn format_time(seconds: f64) -> String {
+    match seconds {
+        s if s < 1e-6 => format!("{:.1} ns", 1e9 * s),
+        s if s < 1e-3 => format!("{:.2} µs", 1e6 * s),
+        s => format!("{:.3} ms", 1e3 * s),
+    }
+}
@@ -0,0 +1,67 @@
+//! Micro-benchmarks of the hot paths of collision detection and mass properties.
+
+mod common;
+
+use std::hint::black_box;
+
+use cgmath::{vec3, Deg, Euler};
+use constraint_solver::{
+    collision::{edge_axes_separation, face_axes_separation},
+    frame::Frame,
+    geometry::Polytope,
+};
+
+fn main() {
+    let mut bench = common::Bench::from_args();
+
+    // Two boxes overlapping at an angle, as in a typical contact.
+    let boxes = (
+        Polytope::new_box(vec3(0.5, 0.3, 0.2)),
+        Polytope::new_box(vec3(0.4, 0.4, 0.4)),
+    );
+    let frames = (
+        Frame::default(),
+        Frame {
+            position: vec3(0.3, 0.2, 0.5),
+            rotation: Euler::new(Deg(20.0), Deg(-35.0), Deg(50.0)).into(),
+        },
+    );
+    let sphere = Polytope::new_geodesic_sphere(2);
+
+    bench.measure("face_axes_separation/box", || {
+        black_box(face_axes_separation(
+            black_box(frames),
+            (&boxes.0, &boxes.1),
+        ));
+    });
+    bench.measure("face_axes_separation/sphere", || {
+        black_box(face_axes_separation(black_box(frames), (&sphere, &sphere)));
+    });
+
+    bench.measure("edge_axes_separation/box", || {
+        black_box(edge_axes_separation(
+            black_box(frames),
+            (&boxes.0, &boxes.1),
+        ));
+    });
+    bench.measure("edge_axes_separation/sphere", || {
+        black_box(edge_axes_separation(black_box(frames), (&sphere, &sphere)));
+    });
+
+    let direction = vec3(0.3, -1.0, 0.5);
+    bench.measure("support/box", || {
+        black_box(boxes.0.support(black_box(frames.1), black_box(direction)));
+    });
+    bench.measure("support/sphere", || {
+        black_box(sphere.support(black_box(frames.1), black_box(direction)));
+    });
+
+    bench.measure("rigid_metrics/box", || {
+        black_box(black_box(&boxes.0).rigid_metrics(1.0));
+    });
+    bench.measure("rigid_metrics/sphere", || {
+        black_box(black_box(&sphere).rigid_metrics(1.0));
+    });
+
+    bench.finish();
+}
@@ -0,0 +1,53 @@
+//! Whole-step benchmarks of typical scenes, with the time per step broken down into the phases of the solver.
+//! Every scene is simulated from its initial state, so the results only depend on the code.
+
+mod common;
+
+use std::time::Duration;
+
+use constraint_solver::{debug::DebugLines, scene, solver::Timings, world::World};
+
+const DT: f64 = 1.0 / 60.0;
+
+/// Name, scene and number of steps to simulate.
+type Scenario = (&'static str, fn() -> World, usize);
+
+fn main() {
+    let mut bench = common::Bench::from_args();
+
+    let scenarios: [Scenario; 4] = [
+        ("pyramid", || scene::pyramid(10), 120),
+        ("chain", || scene::chain(20), 300),
+        ("terrain", || scene::terrain(64), 150),
+        ("pile", || scene::pile(24), 120),
+    ];
+
+    for (name, create, steps) in scenarios {
+        let name = format!("scenario/{name}");
+        if !bench.enabled(&name) {
+            continue;
+        }
+
+        let mut world = create();
+        let mut timings = Timings::default();
+        for _ in 0..steps {
+            world.integrate(DT, &mut DebugLines::default());
+            timings += world.timings;
+        }
+
+        let mean = |duration: Duration| duration.as_secs_f64() / steps as f64;
+        bench.report(&name, mean(timings.total));
+        for (phase, duration) in [
+            ("broadphase", timings.broadphase),
+            ("integrate", timings.integrate),
+            ("narrowphase", timings.narrowphase),
+            ("solve", timings.solve),
+            ("velocities", timings.velocities),
+            ("sleep", timings.sleep),
+        ] {
+            bench.report(&format!("{name}/{phase}"), mean(duration));
+        }
+    }
+
+    bench.finish();
+}
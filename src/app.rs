@@ -1,23 +1,26 @@
 use lerp::Lerp;
-use std::{
-    f32::consts::TAU,
-    time::{Duration, Instant},
-};
+use std::{f32::consts::TAU, time::Instant};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
-use crate::{camera, debug, geometry, mesh, renderer, world};
+use crate::{camera, debug, gltf, history, mesh, recorder, renderer, scene, snapshot, world};
 
 pub const CAMERA_RESPONSIVNESS: f32 = 0.5;
-pub const FRAME_TIME: f64 = 1.0 / 60.0;
+/// Duration of a simulation step in `s`, independent of the display rate.
+pub const TIME_STEP: f64 = 1.0 / 60.0;
 pub const DEFAULT_COLOR: [f32; 3] = [0.4; 3];
+/// Where the current state is saved to and restored from.
+pub const SNAPSHOT_PATH: &str = "snapshot.ron";
+/// Where trajectories are recorded to while recording is toggled on.
+pub const RECORDING_PATH: &str = "trajectory.csv";
+/// Where the recorded history is exported to as an animation.
+pub const ANIMATION_PATH: &str = "animation.glb";
 
 pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
-    let mut last_render_time = Instant::now();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title("Constraint Solver")
@@ -26,10 +29,20 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         .unwrap();
 
     let mut renderer = renderer::Renderer::new(&window).await?;
-    let p1 = geometry::Polytope::new_cube();
-    let p2 = 0.5 * geometry::Polytope::new_tetrahedron();
-    let m1 = mesh::Mesh::from_polytope(&renderer, &p1);
-    let m2 = mesh::Mesh::from_polytope(&renderer, &p2);
+    let initial_world = match std::env::args().nth(1) {
+        Some(path) => scene::load(std::path::Path::new(&path))?,
+        None => world::World::new(),
+    };
+
+    // Every rigid needs its own mesh, since meshes carry the uniforms to place them.
+    let create_meshes = |renderer: &renderer::Renderer, world: &world::World| -> Vec<mesh::Mesh> {
+        world
+            .rigids
+            .iter()
+            .map(|rigid| mesh::Mesh::from_compound(renderer, &world.shapes[rigid.shape]))
+            .collect()
+    };
+    let mut meshes = create_meshes(&renderer, &initial_world);
 
     let mut camera = camera::Camera::initial();
     let mut camera_target = camera;
@@ -45,8 +58,15 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
     let mut manual_forward_step = false;
     let mut time_speed_up = false;
 
-    let mut states = vec![(world::World::new(&p1, &p2), debug::DebugLines::default())];
-    let mut current_state: usize = 0;
+    // Only the shown frame and its predecessor are kept at hand, past ones are restored from the history.
+    let mut current = initial_world.clone();
+    let mut previous = initial_world.clone();
+    let mut debug_lines = debug::DebugLines::default();
+    let mut history = history::History::new(initial_world, TIME_STEP);
+    let mut current_frame: usize = 0;
+    let mut recorder: Option<recorder::Recorder<_>> = None;
+    let mut timestep = world::FixedTimestep::new(TIME_STEP);
+    let mut last_update = Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
         let time_speed = || if time_speed_up { 5 } else { 1 };
@@ -144,7 +164,101 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
                         },
                     ..
                 } if paused => {
-                    current_state = current_state.saturating_sub(time_speed());
+                    current_frame = current_frame
+                        .saturating_sub(time_speed())
+                        .max(history.first_frame());
+                    current = history.seek(current_frame).unwrap().clone();
+                    previous = current.clone();
+                    debug_lines = debug::DebugLines::default();
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F5),
+                            ..
+                        },
+                    ..
+                } => {
+                    let path = std::path::Path::new(SNAPSHOT_PATH);
+                    match snapshot::save(&current, path) {
+                        Ok(()) => println!("Saved snapshot to {SNAPSHOT_PATH}"),
+                        Err(error) => println!("{error}"),
+                    }
+                }
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F9),
+                            ..
+                        },
+                    ..
+                } => match snapshot::load(std::path::Path::new(SNAPSHOT_PATH)) {
+                    Ok(world) => {
+                        // The restored world may contain other rigids, so the history is discarded.
+                        meshes = create_meshes(&renderer, &world);
+                        current = world.clone();
+                        previous = world.clone();
+                        debug_lines = debug::DebugLines::default();
+                        history = history::History::new(world, TIME_STEP);
+                        current_frame = 0;
+                        paused = true;
+                        println!("Restored snapshot from {SNAPSHOT_PATH}");
+                    }
+                    Err(error) => println!("{error}"),
+                },
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::R),
+                            ..
+                        },
+                    ..
+                } => match recorder.take() {
+                    Some(recorder) => match recorder.finish() {
+                        Ok(_) => println!("Recorded trajectories to {RECORDING_PATH}"),
+                        Err(error) => println!("Cannot record trajectories: {error}"),
+                    },
+                    None => {
+                        let path = std::path::Path::new(RECORDING_PATH);
+                        match recorder::Recorder::create(path, TIME_STEP) {
+                            Ok(mut created) => match created.record(current_frame, &current) {
+                                Ok(()) => recorder = Some(created),
+                                Err(error) => println!("Cannot record trajectories: {error}"),
+                            },
+                            Err(error) => println!("Cannot record trajectories: {error}"),
+                        }
+                    }
+                },
+
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::G),
+                            ..
+                        },
+                    ..
+                } => {
+                    // Exports all frames still reachable in the history, including those ahead of the shown one.
+                    let first_frame = history.first_frame();
+                    let last_frame = history.last_frame();
+                    let mut animation =
+                        gltf::Animation::new(history.seek(first_frame).unwrap(), TIME_STEP);
+                    for frame in first_frame + 1..=last_frame {
+                        animation.record(history.seek(frame).unwrap());
+                    }
+                    match animation.save(std::path::Path::new(ANIMATION_PATH)) {
+                        Ok(()) => println!(
+                            "Exported frames {first_frame} to {last_frame} to {ANIMATION_PATH}"
+                        ),
+                        Err(error) => println!("Cannot export animation: {error}"),
+                    }
                 }
 
                 WindowEvent::ModifiersChanged(state) => {
@@ -199,19 +313,37 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
             },
 
             Event::RedrawRequested(..) => {
-                last_render_time = Instant::now();
-
-                if !paused || manual_forward_step {
-                    for _ in 0..time_speed() {
-                        if current_state + 1 >= states.len() {
-                            let mut world = states[current_state].0;
-                            let mut debug_lines = debug::DebugLines::default();
-                            world.integrate(FRAME_TIME, &p1, &p2, &mut debug_lines);
-                            states.push((world, debug_lines));
+                let elapsed = last_update.elapsed().as_secs_f64();
+                last_update = Instant::now();
+
+                let steps = if paused {
+                    if manual_forward_step {
+                        time_speed()
+                    } else {
+                        0
+                    }
+                } else {
+                    timestep.advance(elapsed * time_speed() as f64)
+                };
+                manual_forward_step = false;
+
+                for _ in 0..steps {
+                    debug_lines = debug::DebugLines::default();
+                    let next = if current_frame == history.last_frame() {
+                        history.advance(&mut debug_lines);
+                        history.latest().clone()
+                    } else {
+                        history.seek(current_frame + 1).unwrap().clone()
+                    };
+                    previous = std::mem::replace(&mut current, next);
+                    current_frame += 1;
+
+                    if let Some(active) = &mut recorder {
+                        if let Err(error) = active.record(current_frame, &current) {
+                            println!("Cannot record trajectories: {error}");
+                            recorder = None;
                         }
-                        current_state += 1;
                     }
-                    manual_forward_step = false;
                 }
 
                 camera.orbit = camera.orbit.lerp(camera_target.orbit, CAMERA_RESPONSIVNESS);
@@ -222,14 +354,23 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
                     .distance
                     .lerp(camera_target.distance, CAMERA_RESPONSIVNESS);
 
-                let (world, debug_lines) = &states[current_state];
+                // Render between the last two states, by the time accumulated towards the next one.
+                let world = &current;
+                let alpha = if paused { 1.0 } else { timestep.alpha() };
 
-                let geometry = [
-                    (&m1, world.a.frame(), world.a.color.unwrap_or(DEFAULT_COLOR)),
-                    (&m2, world.b.frame(), world.b.color.unwrap_or(DEFAULT_COLOR)),
-                ];
+                let geometry: Vec<_> = meshes
+                    .iter()
+                    .zip(previous.rigids.iter().zip(&world.rigids))
+                    .map(|(mesh, (previous, rigid))| {
+                        (
+                            mesh,
+                            previous.interpolated_frame(rigid, alpha),
+                            rigid.color.unwrap_or(DEFAULT_COLOR),
+                        )
+                    })
+                    .collect();
 
-                match renderer.render(&camera, &geometry, debug_lines) {
+                match renderer.render(&camera, &geometry, &debug_lines) {
                     Ok(_) => {}
                     Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size),
                     Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
@@ -238,15 +379,7 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
             }
 
             Event::MainEventsCleared => {
-                let target_frametime = Duration::from_secs_f64(FRAME_TIME);
-                let time_since_last_frame = last_render_time.elapsed();
-                if time_since_last_frame >= target_frametime {
-                    window.request_redraw();
-                } else {
-                    *control_flow = ControlFlow::WaitUntil(
-                        Instant::now() + target_frametime - time_since_last_frame,
-                    );
-                }
+                window.request_redraw();
             }
 
             _ => {}
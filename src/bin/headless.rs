@@ -0,0 +1,278 @@
+//! Steps a scene without opening a window and reports trajectories, timings and world hashes.
+//!
+//! Every step writes a line `step <index> <hash> <seconds>`,
+//! followed by a line `rigid <index> <position> <rotation>` per rigid unless trajectories are disabled.
+//! A summary is written to stderr at the end, including how far energy and momentum drifted.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    process::ExitCode,
+    time::Instant,
+};
+
+use cgmath::{Vector3, Zero};
+use constraint_solver::{
+    debug::DebugLines,
+    diagnostics::Drift,
+    gltf::Animation,
+    recorder::{self, Recorder},
+    scene, snapshot,
+    world::World,
+};
+
+const USAGE: &str = "\
+Usage: headless [options]
+
+Options:
+    --scene <name>       Built-in scene or .ron/.json scene file to simulate (default: demo)
+    --frames <count>     Number of steps to simulate (default: 600)
+    --dt <seconds>       Duration of a step (default: 1/60)
+    --substeps <count>   Substeps per step (default: from the solver config)
+    --output <path>      Write to a file instead of stdout
+    --restore <path>     Start from a snapshot instead of a scene
+    --save <path>        Save a snapshot of the final state
+    --record <path>      Log the state of every rigid per step to a .csv or .bin file
+    --gltf <path>        Export the simulation as a .glb or .gltf animation
+    --no-trajectories    Only report hashes and timings
+    --help               Print this message";
+
+struct Options {
+    scene: String,
+    frames: usize,
+    dt: f64,
+    substeps: Option<usize>,
+    output: Option<String>,
+    restore: Option<String>,
+    save: Option<String>,
+    record: Option<String>,
+    gltf: Option<String>,
+    trajectories: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            scene: "demo".to_string(),
+            frames: 600,
+            dt: 1.0 / 60.0,
+            substeps: None,
+            output: None,
+            restore: None,
+            save: None,
+            record: None,
+            gltf: None,
+            trajectories: true,
+        }
+    }
+}
+
+/// Parses the command line arguments, or returns `None` if the usage should be printed.
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Options>, String> {
+    fn value<T: std::str::FromStr>(
+        args: &mut impl Iterator<Item = String>,
+        option: &str,
+    ) -> Result<T, String> {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {option}"))?;
+        value
+            .parse()
+            .map_err(|_| format!("Invalid value for {option}: {value}"))
+    }
+
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scene" => options.scene = value(&mut args, &arg)?,
+            "--frames" => options.frames = value(&mut args, &arg)?,
+            "--dt" => options.dt = value(&mut args, &arg)?,
+            "--substeps" => options.substeps = Some(value(&mut args, &arg)?),
+            "--output" => options.output = Some(value(&mut args, &arg)?),
+            "--restore" => options.restore = Some(value(&mut args, &arg)?),
+            "--save" => options.save = Some(value(&mut args, &arg)?),
+            "--record" => options.record = Some(value(&mut args, &arg)?),
+            "--gltf" => options.gltf = Some(value(&mut args, &arg)?),
+            "--no-trajectories" => options.trajectories = false,
+            "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {arg}")),
+        }
+    }
+
+    if !(options.dt > 0.0 && options.dt.is_finite()) {
+        return Err("The step duration must be positive and finite".to_string());
+    }
+    if options.substeps == Some(0) {
+        return Err("There must be at least one substep".to_string());
+    }
+    if let Some(path) = &options.record {
+        if recorder::Format::from_path(Path::new(path)).is_none() {
+            return Err(format!("Expected a .csv or .bin file to record to: {path}"));
+        }
+    }
+    if let Some(path) = &options.gltf {
+        if !matches!(
+            Path::new(path)
+                .extension()
+                .and_then(|extension| extension.to_str()),
+            Some("glb" | "gltf")
+        ) {
+            return Err(format!(
+                "Expected a .glb or .gltf file to export to: {path}"
+            ));
+        }
+    }
+    Ok(Some(options))
+}
+
+/// Simulates the world, writing its trajectory to `out`.
+/// Errors name the file which could not be written.
+fn run(options: &Options, world: &mut World, out: &mut impl Write) -> Result<(), String> {
+    if let Some(substeps) = options.substeps {
+        world.config.substeps = substeps;
+    }
+    let output = options.output.as_deref().unwrap_or("standard output");
+    let write_error = |error: io::Error| format!("Cannot write to {output}: {error}");
+    let record_error = |error: io::Error| {
+        let path = options.record.as_deref().unwrap_or_default();
+        format!("Cannot record to {path}: {error}")
+    };
+
+    // The recording starts with the initial state, so its step numbers count the steps taken.
+    let mut recorder = match &options.record {
+        Some(path) => Some(Recorder::create(Path::new(path), options.dt).map_err(record_error)?),
+        None => None,
+    };
+    if let Some(recorder) = &mut recorder {
+        recorder.record(0, world).map_err(record_error)?;
+    }
+    let mut animation = options
+        .gltf
+        .as_ref()
+        .map(|_| Animation::new(world, options.dt));
+
+    let mut drift = Drift::new(world, Vector3::zero());
+    let mut total = 0.0;
+    let mut slowest: f64 = 0.0;
+    for step in 0..options.frames {
+        let start = Instant::now();
+        let impulse = world.external_impulse(options.dt);
+        world.integrate(options.dt, &mut DebugLines::default());
+        let seconds = start.elapsed().as_secs_f64();
+        total += seconds;
+        slowest = slowest.max(seconds);
+        drift.record(world, impulse);
+
+        if let Some(recorder) = &mut recorder {
+            recorder.record(step + 1, world).map_err(record_error)?;
+        }
+        if let Some(animation) = &mut animation {
+            animation.record(world);
+        }
+
+        writeln!(out, "step {step} {:016x} {seconds:.9}", world.hash()).map_err(write_error)?;
+        if options.trajectories {
+            for (index, rigid) in world.rigids.iter().enumerate() {
+                let p = rigid.position;
+                let q = rigid.rotation;
+                writeln!(
+                    out,
+                    "rigid {index} {} {} {} {} {} {} {}",
+                    p.x, p.y, p.z, q.s, q.v.x, q.v.y, q.v.z
+                )
+                .map_err(write_error)?;
+            }
+        }
+    }
+    out.flush().map_err(write_error)?;
+    if let Some(recorder) = recorder {
+        recorder.finish().map_err(record_error)?;
+    }
+    if let (Some(animation), Some(path)) = (&animation, &options.gltf) {
+        animation
+            .save(Path::new(path))
+            .map_err(|error| format!("Cannot export to {path}: {error}"))?;
+    }
+
+    eprintln!(
+        "Simulated {} steps of {} rigids in {total:.3} s (mean {:.3} ms, slowest {:.3} ms), final hash {:016x}",
+        options.frames,
+        world.rigids.len(),
+        1e3 * total / options.frames.max(1) as f64,
+        1e3 * slowest,
+        world.hash()
+    );
+    let relative_energy = drift
+        .relative_energy()
+        .map(|relative| format!(" ({:+.3} %)", 100.0 * relative))
+        .unwrap_or_default();
+    eprintln!(
+        "Energy changed by {:+.6} J{relative_energy}, at most gained {:.6} J and lost {:.6} J; momentum deviated from gravity and applied forces by up to {:.6} kg m/s horizontally and {:.6} kg m/s vertically, where the ground supports resting rigids",
+        drift.energy(),
+        drift.max_energy_gain,
+        drift.max_energy_loss,
+        drift.max_horizontal_momentum_error,
+        drift.max_vertical_momentum_error
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Built-in scenes are only built if no snapshot replaces them.
+    let world = match &options.restore {
+        Some(path) => snapshot::load(Path::new(path)).map_err(|error| error.to_string()),
+        None => match scene::by_name(&options.scene) {
+            Some(world) => Ok(world),
+            None if Path::new(&options.scene).is_file() => {
+                scene::load(Path::new(&options.scene)).map_err(|error| error.to_string())
+            }
+            None => Err(format!(
+                "Unknown scene: {}, expected a scene file or one of {}",
+                options.scene,
+                scene::NAMES.join(", ")
+            )),
+        },
+    };
+    let mut world = match world {
+        Ok(world) => world,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = match &options.output {
+        Some(path) => File::create(path)
+            .map_err(|error| format!("Cannot create {path}: {error}"))
+            .and_then(|file| run(&options, &mut world, &mut BufWriter::new(file))),
+        None => run(
+            &options,
+            &mut world,
+            &mut BufWriter::new(io::stdout().lock()),
+        ),
+    };
+    if let Err(error) = result {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+    if let Some(path) = &options.save {
+        if let Err(error) = snapshot::save(&world, Path::new(path)) {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -5,15 +5,15 @@ use crate::{
     constraint::Constraint,
     debug,
     frame::Frame,
-    geometry::{self, Plane, Polytope},
+    geometry::{compound::Compound, heightfield::Heightfield, Aabb, Plane, Polytope},
     rigid::Rigid,
 };
 
-#[allow(dead_code)]
-pub fn ground(rigid: &Rigid, past: Frame, polytope: &geometry::Polytope) -> Vec<Constraint> {
+/// Generates contacts for all vertices of a rigid which penetrate the ground plane at `z = 0`.
+pub fn ground(index: usize, rigid: &Rigid, past: Frame, compound: &Compound) -> Vec<Constraint> {
     let mut constraints = Vec::new();
 
-    for &vertex in &polytope.vertices {
+    for vertex in compound.vertices() {
         let position = rigid.frame() * vertex;
         if position.z >= 0.0 {
             continue;
@@ -24,16 +24,286 @@ pub fn ground(rigid: &Rigid, past: Frame, polytope: &geometry::Polytope) -> Vec<
         let delta_position = rigid.frame().delta(past, position);
         let delta_tangential_position = delta_position - delta_position.project_on(correction);
 
-        constraints.push(Constraint {
-            rigid: 0,
-            contacts: (position, target_position - 1.0 * delta_tangential_position),
-            distance: 0.0,
-        })
+        constraints.push(
+            Constraint::attach(
+                index,
+                vertex,
+                target_position - 1.0 * delta_tangential_position,
+                0.0,
+            )
+            .normal(Some(Vector3::unit_z())),
+        )
+    }
+
+    constraints
+}
+
+/// Generates contacts for all vertices of a rigid which penetrate a heightfield,
+/// pushing them out along the normal of the triangle beneath.
+/// Only vertices are tested, so sharp peaks of the terrain may poke into large faces.
+pub fn terrain(
+    index: usize,
+    rigid: &Rigid,
+    past: Frame,
+    compound: &Compound,
+    heightfield: &Heightfield,
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+
+    for vertex in compound.vertices() {
+        let position = rigid.frame() * vertex;
+        let Some(plane) = heightfield.plane(position.x, position.y) else {
+            continue;
+        };
+        let distance = plane.distance(position);
+        if distance >= 0.0 {
+            continue;
+        }
+
+        let target_position = position - distance * plane.normal;
+        let correction = target_position - position;
+        let delta_position = rigid.frame().delta(past, position);
+        let delta_tangential_position = delta_position - delta_position.project_on(correction);
+
+        constraints.push(
+            Constraint::attach(
+                index,
+                vertex,
+                target_position - delta_tangential_position,
+                0.0,
+            )
+            .normal(Some(plane.normal)),
+        )
     }
 
     constraints
 }
 
+/// Penetration in `m` by which the faces of the first part may be worse than those of the second one
+/// before they serve as reference instead. This avoids flip-flopping between nearly parallel faces.
+const REFERENCE_BIAS: f64 = 1e-3;
+
+/// Generates contacts between two rigids, each composed of convex parts.
+/// For each pair of overlapping parts, the face with the least penetration found by the separating axis test
+/// serves as reference face, and the face of the other part most opposed to it as incident face.
+/// The incident face is clipped against the sides of the reference face,
+/// and all clipped points behind the reference face are pushed out along its normal.
+/// If a pair of crossing edges penetrates less than any face, their closest points are pushed apart instead.
+pub fn penetrations(
+    indices: (usize, usize),
+    frames: (Frame, Frame),
+    past: (Frame, Frame),
+    compounds: (&Compound, &Compound),
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+
+    for (a_offset, a) in &compounds.0.parts {
+        for (b_offset, b) in &compounds.1.parts {
+            let a = Part {
+                rigid: indices.0,
+                frame: frames.0,
+                past: past.0,
+                offset: *a_offset,
+                polytope: a,
+            };
+            let b = Part {
+                rigid: indices.1,
+                frame: frames.1,
+                past: past.1,
+                offset: *b_offset,
+                polytope: b,
+            };
+
+            let a_query = face_axes_separation((a.world(), b.world()), (a.polytope, b.polytope));
+            let b_query = face_axes_separation((b.world(), a.world()), (b.polytope, a.polytope));
+            if a_query.0 >= 0.0 || b_query.0 >= 0.0 {
+                continue;
+            }
+            let edge_query = edge_axes_separation((a.world(), b.world()), (a.polytope, b.polytope));
+            if edge_query.0 >= 0.0 {
+                continue;
+            }
+
+            if edge_query.0 > a_query.0.max(b_query.0) + REFERENCE_BIAS {
+                constraints.extend(edge_contact(a, b, edge_query.1));
+                continue;
+            }
+
+            let (reference, incident, reference_face) = if b_query.0 + REFERENCE_BIAS >= a_query.0 {
+                (b, a, b_query.1)
+            } else {
+                (a, b, a_query.1)
+            };
+            constraints.extend(face_contacts(reference, reference_face, incident));
+        }
+    }
+
+    constraints
+}
+
+/// A convex part of a rigid taking part in a collision.
+#[derive(Clone, Copy)]
+struct Part<'a> {
+    rigid: usize,
+
+    /// The current and past frame of the rigid.
+    frame: Frame,
+    past: Frame,
+
+    /// The frame of the part in object space.
+    offset: Frame,
+
+    polytope: &'a Polytope,
+}
+
+impl Part<'_> {
+    /// The frame transforming from part space to world space.
+    fn world(&self) -> Frame {
+        self.frame * self.offset
+    }
+}
+
+fn face_contacts(reference: Part, reference_face: usize, incident: Part) -> Vec<Constraint> {
+    let reference_plane = reference.world() * reference.polytope.plane(reference_face);
+    let normal = reference_plane.normal;
+
+    let incident_face = incident
+        .polytope
+        .planes()
+        .map(|plane| (incident.world() * plane).normal.dot(normal))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap()
+        .0;
+
+    let reference_polygon: Vec<Vector3<f64>> = reference
+        .polytope
+        .face(reference_face)
+        .map(|v| reference.world() * v)
+        .collect();
+    let mut polygon: Vec<Vector3<f64>> = incident
+        .polytope
+        .face(incident_face)
+        .map(|v| incident.world() * v)
+        .collect();
+    for (p, q) in reference_polygon.into_iter().circular_tuple_windows() {
+        polygon = clip(
+            &polygon,
+            Plane::from_point_normal(p, (q - p).cross(normal).normalize()),
+        );
+    }
+
+    polygon
+        .into_iter()
+        .filter_map(|position| {
+            let depth = reference_plane.distance(position);
+            (depth < 0.0).then(|| contact(reference, incident, position, normal, depth))
+        })
+        .collect()
+}
+
+/// Pushes the closest points of an edge of `a` and an edge of `b` apart along their common normal.
+fn edge_contact(a: Part, b: Part, edges: (usize, usize)) -> Option<Constraint> {
+    let edge = |part: Part, (i, j): (usize, usize)| {
+        (
+            part.world() * part.polytope.vertices[i],
+            part.world() * part.polytope.vertices[j],
+        )
+    };
+    let (a_start, a_end) = edge(a, a.polytope.edges[edges.0]);
+    let (b_start, b_end) = edge(b, b.polytope.edges[edges.1]);
+    let (a_point, b_point) = closest_points((a_start, a_end), (b_start, b_end));
+
+    let mut normal = (a_end - a_start).cross(b_end - b_start).normalize();
+    if normal.dot(a_start - a.world() * a.polytope.centroid) < 0.0 {
+        normal = -normal;
+    }
+    let depth = (b_point - a_point).dot(normal);
+    (depth < 0.0).then(|| contact(a, b, b_point, normal, depth))
+}
+
+/// Connects a point on the incident rigid, lying `depth` behind the reference surface,
+/// to the point on the surface it is pushed to, cancelling the tangential motion between both rigids.
+fn contact(
+    reference: Part,
+    incident: Part,
+    position: Vector3<f64>,
+    normal: Vector3<f64>,
+    depth: f64,
+) -> Constraint {
+    let target_position = position - depth * normal;
+    let delta_position = incident.frame.delta(incident.past, position)
+        - reference.frame.delta(reference.past, target_position);
+    let delta_tangential_position = delta_position - delta_position.project_on(normal);
+
+    Constraint::connect(
+        (incident.rigid, reference.rigid),
+        (
+            incident.frame.inverse() * position,
+            reference.frame.inverse() * (target_position - delta_tangential_position),
+        ),
+        0.0,
+    )
+    .normal(Some(normal))
+}
+
+/// The closest points of two segments, clamped to their ends.
+fn closest_points(
+    a: (Vector3<f64>, Vector3<f64>),
+    b: (Vector3<f64>, Vector3<f64>),
+) -> (Vector3<f64>, Vector3<f64>) {
+    let (u, v, w) = (a.1 - a.0, b.1 - b.0, a.0 - b.0);
+    let (uu, uv, vv, uw, vw) = (u.dot(u), u.dot(v), v.dot(v), u.dot(w), v.dot(w));
+    let denominator = uu * vv - uv * uv;
+
+    let s = if denominator > 0.0 {
+        ((uv * vw - vv * uw) / denominator).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let t = ((uv * s + vw) / vv).clamp(0.0, 1.0);
+    let s = ((uv * t - uw) / uu).clamp(0.0, 1.0);
+
+    (a.0 + s * u, b.0 + t * v)
+}
+
+/// Clips a convex polygon to the back side of a plane.
+fn clip(polygon: &[Vector3<f64>], plane: Plane) -> Vec<Vector3<f64>> {
+    let mut clipped = Vec::new();
+    for (p, q) in polygon.iter().copied().circular_tuple_windows() {
+        let (p_distance, q_distance) = (plane.distance(p), plane.distance(q));
+        if p_distance <= 0.0 {
+            clipped.push(p);
+        }
+        if (p_distance <= 0.0) != (q_distance <= 0.0) {
+            clipped.push(p + p_distance / (p_distance - q_distance) * (q - p));
+        }
+    }
+    clipped
+}
+
+/// Finds all pairs of overlapping boxes by sorting them along the x-axis.
+/// Pairs are ordered and given by ascending indices.
+pub fn sweep_and_prune(boxes: &[Aabb]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..boxes.len()).collect();
+    order.sort_by(|&a, &b| boxes[a].min.x.total_cmp(&boxes[b].min.x));
+
+    let mut pairs = Vec::new();
+    for (i, &a) in order.iter().enumerate() {
+        for &b in &order[i + 1..] {
+            if boxes[b].min.x > boxes[a].max.x {
+                break;
+            }
+            if boxes[a].overlaps(&boxes[b]) {
+                pairs.push((a.min(b), a.max(b)));
+            }
+        }
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
+
 pub fn sat(
     frames: (Frame, Frame),
     polytopes: (&Polytope, &Polytope),
@@ -148,10 +418,12 @@ pub fn face_axes_separation(
     (max_distance, face_index)
 }
 
+/// Finds the pair of edges whose common normal separates the polytopes the most.
+/// Only pairs which are extremal along that normal on both polytopes are considered,
+/// so that the axis is an actual face of their Minkowski difference.
 pub fn edge_axes_separation(
     frames: (Frame, Frame),
     polytopes: (&Polytope, &Polytope),
-    _debug: &mut debug::DebugLines,
 ) -> (f64, (usize, usize)) {
     let mut max_distance = f64::MIN;
     let mut edge_indices = (usize::MAX, usize::MAX);
@@ -171,21 +443,32 @@ pub fn edge_axes_separation(
             frames.1 * polytopes.1.vertices[j.1] - frames.1 * polytopes.1.vertices[j.0],
         );
 
-        let mut axis = edges.0.cross(edges.1).normalize();
+        // Parallel edges do not span an axis.
+        let axis = edges.0.cross(edges.1);
+        if axis.magnitude2() <= 1e-18 * edges.0.magnitude2() * edges.1.magnitude2() {
+            continue;
+        }
+        let mut axis = axis.normalize();
 
         // Keep normal pointing from `a` to `b`.
         if axis.dot(foot - frames.0 * polytopes.0.centroid) < 0.0 {
             axis = -axis;
         }
 
-        // Ignore if another point on `a` is further in the direction to `b`.
-        if polytopes.0.support(frames.0, axis).dot(axis) > foot.dot(axis) {
+        // Ignore if another point on `a` is further in the direction to `b`,
+        // or another point on `b` is further in the direction to `a`.
+        let tolerance = 1e-9 * (1.0 + foot.magnitude());
+        let b_foot = frames.1 * polytopes.1.vertices[j.0];
+        let b_support = polytopes.1.support(frames.1, -axis);
+        if polytopes.0.support(frames.0, axis).dot(axis) > foot.dot(axis) + tolerance
+            || b_support.dot(axis) < b_foot.dot(axis) - tolerance
+        {
             continue;
         }
 
         let plane = Plane::from_point_normal(foot, axis);
 
-        let distance = plane.distance(polytopes.1.support(frames.1, -axis));
+        let distance = plane.distance(b_support);
 
         if distance > max_distance {
             max_distance = distance;
@@ -195,3 +478,125 @@ pub fn edge_axes_separation(
 
     (max_distance, edge_indices)
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+    use super::{clip, penetrations, sweep_and_prune};
+    use crate::{
+        frame::Frame,
+        geometry::{compound::Compound, Aabb, Plane, Polytope},
+        rigid::Rigid,
+    };
+
+    #[test]
+    fn clipping_keeps_the_back_side() {
+        let square = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        ];
+        let clipped = clip(
+            &square,
+            Plane::from_point_normal(vec3(0.25, 0.0, 0.0), vec3(1.0, 0.0, 0.0)),
+        );
+
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|p| p.x <= 0.25));
+        assert!(clipped.contains(&vec3(0.25, 0.0, 0.0)));
+        assert!(clipped.contains(&vec3(0.25, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn contacts_are_clipped_to_the_reference_face() {
+        // A small cube sinking 5 cm into a wide slab, so the slab's top face is clipped to the cube's bottom face.
+        let slab: Compound = Polytope::new_box(vec3(1.0, 1.0, 0.5)).into();
+        let cube: Compound = Polytope::new_box(vec3(0.5, 0.5, 0.5)).into();
+        let rigids = [
+            Rigid::new(slab.rigid_metrics(1.0)).unwrap(),
+            Rigid::new(cube.rigid_metrics(1.0))
+                .unwrap()
+                .position(vec3(0.2, 0.1, 0.95)),
+        ];
+        let frames = (rigids[0].frame(), rigids[1].frame());
+
+        let constraints = penetrations((0, 1), frames, frames, (&slab, &cube));
+        assert_eq!(constraints.len(), 4);
+        for constraint in &constraints {
+            assert_eq!(constraint.rigids, (0, Some(1)));
+            let (slab_point, cube_point) = constraint.points(&rigids);
+            assert!((slab_point.z - 0.5).abs() < 1e-12);
+            assert!((cube_point - (slab_point - vec3(0.0, 0.0, 0.05))).magnitude() < 1e-12);
+            assert!((slab_point.x - 0.2).abs() <= 0.5 + 1e-12);
+            assert!((slab_point.y - 0.1).abs() <= 0.5 + 1e-12);
+        }
+    }
+
+    #[test]
+    fn separated_rigids_have_no_contacts() {
+        let cube: Compound = Polytope::new_box(vec3(0.5, 0.5, 0.5)).into();
+        let frames = (
+            Frame::default(),
+            Frame::default().position(vec3(0.6, 0.7, 1.01)),
+        );
+        assert!(penetrations((0, 1), frames, frames, (&cube, &cube)).is_empty());
+    }
+
+    #[test]
+    fn crossed_edges_are_pushed_apart() {
+        // Two cubes standing on edge, rotated so that their edges cross at right angles and overlap by 2 cm.
+        let cube: Compound = Polytope::new_box(vec3(0.5, 0.5, 0.5)).into();
+        let rigids = [
+            Rigid::new(cube.rigid_metrics(1.0))
+                .unwrap()
+                .rotation(Quaternion::from_angle_x(Deg(45.0))),
+            Rigid::new(cube.rigid_metrics(1.0))
+                .unwrap()
+                .position(vec3(0.0, 0.0, 2.0_f64.sqrt() - 0.02))
+                .rotation(Quaternion::from_angle_y(Deg(45.0))),
+        ];
+        let frames = (rigids[0].frame(), rigids[1].frame());
+
+        let constraints = penetrations((0, 1), frames, frames, (&cube, &cube));
+        assert_eq!(constraints.len(), 1);
+        let constraint = constraints[0];
+        let normal = constraint.normal.unwrap();
+        let (upper_point, lower_point) = constraint.points(&rigids);
+
+        assert_eq!(constraint.rigids, (1, Some(0)));
+        assert!((normal - Vector3::unit_z()).magnitude() < 1e-9);
+        assert!((upper_point - vec3(0.0, 0.0, 0.5 * 2.0_f64.sqrt() - 0.02)).magnitude() < 1e-9);
+        assert!((lower_point - vec3(0.0, 0.0, 0.5 * 2.0_f64.sqrt())).magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn sweep_and_prune_finds_all_overlapping_pairs() {
+        let boxes: Vec<Aabb> = (0..40)
+            .map(|i| {
+                let center = vec3(
+                    (i * 37 % 11) as f64 * 0.7,
+                    (i * 17 % 7) as f64 * 0.9,
+                    (i * 29 % 5) as f64,
+                );
+                let half = Vector3::new(0.5, 0.5, 0.5) * (1.0 + (i % 3) as f64 * 0.3);
+                Aabb {
+                    min: center - half,
+                    max: center + half,
+                }
+            })
+            .collect();
+
+        let mut expected = Vec::new();
+        for a in 0..boxes.len() {
+            for b in a + 1..boxes.len() {
+                if boxes[a].overlaps(&boxes[b]) {
+                    expected.push((a, b));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(sweep_and_prune(&boxes), expected);
+    }
+}
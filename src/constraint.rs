@@ -1,38 +1,180 @@
-use cgmath::{InnerSpace, Vector3};
+use cgmath::{InnerSpace, Vector3, Zero};
+use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
 
 use crate::rigid::Rigid;
 
-#[derive(Debug)]
+/// A distance constraint between two attachment points.
+/// Contacts are generated anew every substep, while joints are persistent constraints stored in the world.
+#[derive(Debug, Clone, Copy, Setters, Serialize, Deserialize)]
 pub struct Constraint {
-    pub rigid: usize,
-    pub contacts: (Vector3<f64>, Vector3<f64>),
+    /// The constrained rigids.
+    /// The second rigid is absent if the constraint attaches the first one to the world itself.
+    pub rigids: (usize, Option<usize>),
+
+    /// Attachment points in the object space of the respective rigid,
+    /// or in world space if there is no rigid.
+    pub anchors: (Vector3<f64>, Vector3<f64>),
+
+    /// The distance the attachment points are kept apart.
     pub distance: f64,
+
+    /// Inverse stiffness of the constraint in `m N^-1`.
+    /// A compliance of zero yields an infinitely stiff constraint.
+    pub compliance: f64,
+
+    /// The surface normal of a contact, pointing in the direction the first rigid is pushed.
+    /// Only contacts have a normal, which makes them subject to restitution.
+    pub normal: Option<Vector3<f64>>,
 }
 
 impl Constraint {
-    fn difference(&self) -> Vector3<f64> {
-        self.contacts.1 - self.contacts.0
+    /// Contacts are resolved almost rigidly.
+    pub const CONTACT_COMPLIANCE: f64 = 1e-6;
+
+    /// Attaches a point on a rigid to a point in world space.
+    pub fn attach(
+        rigid: usize,
+        anchor: Vector3<f64>,
+        target: Vector3<f64>,
+        distance: f64,
+    ) -> Constraint {
+        Constraint {
+            rigids: (rigid, None),
+            anchors: (anchor, target),
+            distance,
+            compliance: Self::CONTACT_COMPLIANCE,
+            normal: None,
+        }
+    }
+
+    /// Connects points on two rigids.
+    pub fn connect(
+        rigids: (usize, usize),
+        anchors: (Vector3<f64>, Vector3<f64>),
+        distance: f64,
+    ) -> Constraint {
+        Constraint {
+            rigids: (rigids.0, Some(rigids.1)),
+            anchors,
+            distance,
+            compliance: Self::CONTACT_COMPLIANCE,
+            normal: None,
+        }
+    }
+
+    /// The attachment points in world space.
+    pub fn points(&self, rigids: &[Rigid]) -> (Vector3<f64>, Vector3<f64>) {
+        let first = rigids[self.rigids.0].frame() * self.anchors.0;
+        let second = match self.rigids.1 {
+            Some(rigid) => rigids[rigid].frame() * self.anchors.1,
+            None => self.anchors.1,
+        };
+        (first, second)
     }
 
-    fn direction(&self) -> Vector3<f64> {
-        self.difference().normalize()
+    /// Whether the constraint involves the given rigid.
+    pub fn involves(&self, rigid: usize) -> bool {
+        self.rigids.0 == rigid || self.rigids.1 == Some(rigid)
     }
 
-    pub fn current_distance(&self) -> f64 {
-        self.difference().magnitude()
+    /// The generalized inverse mass of both rigids when pulling the attachment points along `direction`.
+    pub fn inverse_resitance(&self, rigids: &[Rigid], direction: Vector3<f64>) -> f64 {
+        let (first, second) = self.points(rigids);
+        let resistance = |rigid: &Rigid, point: Vector3<f64>| {
+            let angular_impulse = (point - rigid.world_center_of_mass()).cross(direction);
+            rigid.inverse_mass
+                + (rigid.inverse_inertia_tensor() * angular_impulse).dot(angular_impulse)
+        };
+
+        resistance(&rigids[self.rigids.0], first)
+            + self
+                .rigids
+                .1
+                .map_or(0.0, |rigid| resistance(&rigids[rigid], second))
     }
 
-    pub fn inverse_resitance(&self, rigids: &[&Rigid]) -> f64 {
-        let rigid = &rigids[self.rigid];
+    /// The velocity of the first attachment point relative to the second one.
+    pub fn relative_velocity(&self, rigids: &[Rigid]) -> Vector3<f64> {
+        let (first, second) = self.points(rigids);
+        let velocity = |rigid: &Rigid, point: Vector3<f64>| {
+            rigid.velocity
+                + rigid
+                    .angular_velocity
+                    .cross(point - rigid.world_center_of_mass())
+        };
 
-        let angular_impulse = rigid.rotation.conjugate()
-            * (self.contacts.0 - (rigid.position + rigid.center_of_mass)).cross(self.direction());
+        velocity(&rigids[self.rigids.0], first)
+            - self
+                .rigids
+                .1
+                .map_or(Vector3::zero(), |rigid| velocity(&rigids[rigid], second))
+    }
 
-        rigid.inverse_mass + (rigid.inverse_inertia * angular_impulse).dot(angular_impulse)
+    /// Moves the attachment points towards each other by applying opposite impulses.
+    pub fn act(&self, rigids: &mut [Rigid], impulse: Vector3<f64>) {
+        let (first, second) = self.points(rigids);
+        rigids[self.rigids.0].apply_impulse(impulse, first);
+        if let Some(rigid) = self.rigids.1 {
+            rigids[rigid].apply_impulse(-impulse, second);
+        }
     }
 
-    pub fn act(&mut self, rigids: &mut [&mut Rigid], factor: f64) {
-        let impulse = factor * self.direction();
-        rigids[self.rigid].apply_impulse(impulse, self.contacts.0);
+    /// Changes the velocities of the attachment points by applying opposite impulses.
+    pub fn act_on_velocities(&self, rigids: &mut [Rigid], impulse: Vector3<f64>) {
+        let (first, second) = self.points(rigids);
+        rigids[self.rigids.0].apply_velocity_impulse(impulse, first);
+        if let Some(rigid) = self.rigids.1 {
+            rigids[rigid].apply_velocity_impulse(-impulse, second);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, InnerSpace};
+
+    use super::Constraint;
+    use crate::{geometry::Polytope, rigid::Rigid};
+
+    fn cubes() -> [Rigid; 2] {
+        let metrics = Polytope::new_box(vec3(0.5, 0.5, 0.5)).rigid_metrics(1.0);
+        [
+            Rigid::new(metrics).unwrap().position(vec3(1.0, 2.0, 3.0)),
+            Rigid::new(metrics).unwrap().position(vec3(-1.0, 0.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn attachments_pin_the_second_anchor_in_world_space() {
+        let rigids = cubes();
+        let constraint = Constraint::attach(0, vec3(0.5, 0.0, 0.0), vec3(0.0, 0.0, 1.0), 0.0);
+
+        assert_eq!(
+            constraint.points(&rigids),
+            (vec3(1.5, 2.0, 3.0), vec3(0.0, 0.0, 1.0))
+        );
+        assert!(constraint.involves(0));
+        assert!(!constraint.involves(1));
+    }
+
+    #[test]
+    fn connections_push_both_rigids_apart_equally() {
+        let mut rigids = cubes();
+        let constraint =
+            Constraint::connect((0, 1), (vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)), 0.0);
+        let (first, second) = constraint.points(&rigids);
+        let direction = (second - first).normalize();
+
+        // Pulling through the centers of mass does not rotate, so only the masses resist.
+        let inverse_resistance = constraint.inverse_resitance(&rigids, direction);
+        assert!((inverse_resistance - 2.0).abs() < 1e-12);
+
+        let center = rigids[0].position + rigids[1].position;
+        constraint.act(&mut rigids, 0.1 * direction);
+        assert!((rigids[0].position + rigids[1].position - center).magnitude() < 1e-12);
+        let (moved, _) = constraint.points(&rigids);
+        assert!((moved - first - 0.1 * direction).magnitude() < 1e-12);
+        assert!((rigids[0].rotation.s - 1.0).abs() < 1e-12);
     }
 }
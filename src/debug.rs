@@ -1,8 +1,11 @@
 use cgmath::{vec3, Quaternion, Vector3, Zero};
 use itertools::Itertools;
 
-use crate::{geometry, renderer};
+use crate::geometry;
+#[cfg(feature = "render")]
+use crate::renderer;
 
+#[cfg(feature = "render")]
 const MAX_VERTEX_COUNT: usize = 4096;
 
 #[derive(Default)]
@@ -10,6 +13,7 @@ pub struct DebugLines {
     vertices: Vec<DebugLineVertex>,
 }
 
+#[cfg(feature = "render")]
 pub struct LineDebugger {
     buffer: wgpu::Buffer,
     pipeline: wgpu::RenderPipeline,
@@ -22,7 +26,9 @@ struct DebugLineVertex {
     color: [f32; 3],
 }
 
+#[cfg(feature = "render")]
 unsafe impl bytemuck::Pod for DebugLineVertex {}
+#[cfg(feature = "render")]
 unsafe impl bytemuck::Zeroable for DebugLineVertex {}
 
 impl DebugLines {
@@ -114,6 +120,7 @@ impl DebugLines {
     }
 }
 
+#[cfg(feature = "render")]
 impl LineDebugger {
     pub fn new(
         device: &wgpu::Device,
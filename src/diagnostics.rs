@@ -0,0 +1,270 @@
+//! Tracks how far energy and momentum drift over the course of a simulation,
+//! which tells whether the solver adds or removes energy.
+
+use cgmath::{InnerSpace, Vector3, Zero};
+
+use crate::world::World;
+
+/// Energy and momentum of a world at one instant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// Measured in `kg m s^-1`.
+    pub momentum: Vector3<f64>,
+
+    /// Measured about the reference point of the drift, in `kg m^2 s^-1`.
+    pub angular_momentum: Vector3<f64>,
+
+    /// Measured in `J`.
+    pub kinetic_energy: f64,
+
+    /// Measured in `J`.
+    pub potential_energy: f64,
+}
+
+impl Measurement {
+    pub fn new(world: &World, point: Vector3<f64>) -> Measurement {
+        Measurement {
+            momentum: world.momentum(),
+            angular_momentum: world.angular_momentum(point),
+            kinetic_energy: world.kinetic_energy(),
+            potential_energy: world.potential_energy(),
+        }
+    }
+
+    /// Total mechanical energy in `J`.
+    pub fn energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+}
+
+/// Compares measurements taken step by step against the initial one.
+#[derive(Debug, Clone)]
+pub struct Drift {
+    /// The point angular momentum is measured about.
+    pub point: Vector3<f64>,
+
+    pub initial: Measurement,
+    pub latest: Measurement,
+
+    /// Largest energy gained since the initial measurement in `J`.
+    pub max_energy_gain: f64,
+
+    /// Largest energy lost since the initial measurement in `J`.
+    pub max_energy_loss: f64,
+
+    /// Impulse exerted by gravity and forces since the initial measurement in `N s`.
+    pub external_impulse: Vector3<f64>,
+
+    /// Largest deviation of the momentum from its initial value plus the external impulse,
+    /// perpendicular to gravity, in `kg m s^-1`.
+    /// Without gravity, this covers the whole deviation.
+    pub max_horizontal_momentum_error: f64,
+
+    /// Largest deviation of the momentum along gravity in `kg m s^-1`.
+    /// The ground and terrain support resting rigids against gravity by contact impulses,
+    /// which are not part of the external impulse and show up here.
+    pub max_vertical_momentum_error: f64,
+
+    /// Largest deviation of the angular momentum from its initial value in `kg m^2 s^-1`.
+    pub max_angular_momentum_error: f64,
+}
+
+impl Drift {
+    pub fn new(world: &World, point: Vector3<f64>) -> Drift {
+        let initial = Measurement::new(world, point);
+        Drift {
+            point,
+            initial,
+            latest: initial,
+            max_energy_gain: 0.0,
+            max_energy_loss: 0.0,
+            external_impulse: Vector3::zero(),
+            max_horizontal_momentum_error: 0.0,
+            max_vertical_momentum_error: 0.0,
+            max_angular_momentum_error: 0.0,
+        }
+    }
+
+    /// Measures the world after a step and updates the extremes.
+    /// `impulse` is the external impulse exerted during the step,
+    /// which has to be taken by `World::external_impulse` before the step clears the accumulators.
+    pub fn record(&mut self, world: &World, impulse: Vector3<f64>) {
+        self.latest = Measurement::new(world, self.point);
+        self.external_impulse += impulse;
+
+        let energy = self.energy();
+        self.max_energy_gain = self.max_energy_gain.max(energy);
+        self.max_energy_loss = self.max_energy_loss.max(-energy);
+
+        let error = self.momentum_error();
+        let vertical = if world.gravity.magnitude2() > 0.0 {
+            error.project_on(world.gravity)
+        } else {
+            Vector3::zero()
+        };
+        self.max_horizontal_momentum_error = self
+            .max_horizontal_momentum_error
+            .max((error - vertical).magnitude());
+        self.max_vertical_momentum_error =
+            self.max_vertical_momentum_error.max(vertical.magnitude());
+        self.max_angular_momentum_error = self
+            .max_angular_momentum_error
+            .max((self.latest.angular_momentum - self.initial.angular_momentum).magnitude());
+    }
+
+    /// Energy gained since the initial measurement in `J`, negative if energy was lost.
+    pub fn energy(&self) -> f64 {
+        self.latest.energy() - self.initial.energy()
+    }
+
+    /// Energy gained since the initial measurement, relative to the initial energy.
+    /// Returns `None` if the initial energy is zero, so that there is nothing to relate to.
+    pub fn relative_energy(&self) -> Option<f64> {
+        let initial = self.initial.energy().abs();
+        (initial > 0.0).then(|| self.energy() / initial)
+    }
+
+    /// The latest momentum minus the initial momentum and the external impulse since, in `kg m s^-1`.
+    pub fn momentum_error(&self) -> Vector3<f64> {
+        self.latest.momentum - self.initial.momentum - self.external_impulse
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, Euler, InnerSpace, Vector3, Zero};
+
+    use super::Drift;
+    use crate::{
+        constraint::Constraint, debug::DebugLines, geometry::Polytope, rigid::Rigid, scene,
+        world::World,
+    };
+
+    const DT: f64 = 1.0 / 60.0;
+
+    fn run(world: &mut World, drift: &mut Drift, steps: usize) {
+        for _ in 0..steps {
+            let impulse = world.external_impulse(DT);
+            world.integrate(DT, &mut DebugLines::default());
+            drift.record(world, impulse);
+        }
+    }
+
+    /// An asymmetric box high above the ground, flying and tumbling.
+    fn projectile() -> World {
+        let mut world = World::default();
+        let shape = world.add_shape(Polytope::new_box(vec3(1.0, 0.5, 0.25)).into());
+        let rigid = Rigid::new(world.shapes[shape].rigid_metrics(2.0))
+            .unwrap()
+            .shape(shape)
+            .position(vec3(0.0, 0.0, 50.0))
+            .rotation(Euler::new(Deg(20.0), Deg(-35.0), Deg(50.0)).into())
+            .velocity(vec3(3.0, -1.0, 8.0))
+            .angular_velocity(vec3(2.0, -3.0, 1.5));
+        world.add_rigid(rigid);
+        world
+    }
+
+    #[test]
+    fn free_flight_conserves_momentum_and_energy() {
+        let mut world = projectile();
+        world.gravity = Vector3::zero();
+        let mut drift = Drift::new(&world, Vector3::zero());
+        run(&mut world, &mut drift, 120);
+
+        assert!(drift.max_horizontal_momentum_error < 1e-9 * drift.initial.momentum.magnitude());
+        assert!(
+            drift.max_angular_momentum_error < 1e-2 * drift.initial.angular_momentum.magnitude()
+        );
+        // The implicit gyroscopic step may lose energy, but must not gain any.
+        assert!(drift.max_energy_gain < 1e-9 * drift.initial.energy());
+        assert!(drift.max_energy_loss < 1e-2 * drift.initial.energy());
+    }
+
+    #[test]
+    fn ballistic_flight_conserves_energy() {
+        let mut world = projectile();
+        let mut drift = Drift::new(&world, Vector3::zero());
+        run(&mut world, &mut drift, 120);
+
+        assert!(world.rigids[0].position.z > 10.0);
+        assert!(drift.max_energy_gain.max(drift.max_energy_loss) < 1e-3 * drift.initial.energy());
+
+        // Gravity only changes the vertical momentum, which is accounted for as external impulse.
+        let mass = 1.0 / world.rigids[0].inverse_mass;
+        let impulse = 120.0 * DT * mass * world.gravity;
+        assert!((drift.external_impulse - impulse).magnitude() < 1e-9 * mass);
+        assert!(drift.max_horizontal_momentum_error < 1e-9 * mass);
+        // Velocities derived from positions round off a little.
+        assert!(drift.max_vertical_momentum_error < 1e-6 * mass);
+    }
+
+    #[test]
+    fn forces_and_impulses_count_as_external() {
+        let mut world = projectile();
+        let center = world.rigids[0].world_center_of_mass();
+        world.rigids[0].external_force = vec3(0.0, 5.0, 0.0);
+        world.rigids[0].add_impulse_at_point(vec3(1.0, 0.0, 0.0), center);
+        let mut drift = Drift::new(&world, Vector3::zero());
+        run(&mut world, &mut drift, 60);
+
+        let bound = 1e-6 * drift.external_impulse.magnitude();
+        assert!(drift.max_horizontal_momentum_error < bound);
+        assert!(drift.max_vertical_momentum_error < bound);
+    }
+
+    #[test]
+    fn worlds_without_energy_have_no_relative_energy() {
+        let mut world = World {
+            gravity: Vector3::zero(),
+            ..Default::default()
+        };
+        let mut drift = Drift::new(&world, Vector3::zero());
+        run(&mut world, &mut drift, 1);
+
+        assert_eq!(drift.energy(), 0.0);
+        assert_eq!(drift.relative_energy(), None);
+    }
+
+    #[test]
+    fn conical_pendulum_keeps_its_energy_and_vertical_angular_momentum() {
+        let mut world = World::default();
+        let cube = world.add_shape(Polytope::new_cube().into());
+        let pivot = vec3(0.0, 0.0, 6.0);
+        let rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+            .unwrap()
+            .shape(cube)
+            .position(vec3(1.5, -0.5, 5.5))
+            .velocity(vec3(0.0, 2.0, 0.0));
+        let center = rigid.center_of_mass;
+        world.add_rigid(rigid);
+        // Attached at the center of mass, so the joint exerts no torque about the pivot's vertical axis.
+        world.add_joint(Constraint::attach(0, center, pivot, 2.0).compliance(0.0));
+
+        let mut drift = Drift::new(&world, pivot);
+        // The energy the pendulum swings with, relative to its lowest possible position.
+        let swing = drift.initial.energy() + world.gravity.z * (pivot.z - 2.0);
+        run(&mut world, &mut drift, 300);
+
+        let distance = (world.rigids[0].world_center_of_mass() - pivot).magnitude();
+        assert!((distance - 2.0).abs() < 1e-6);
+        // Projecting positions onto the constraint removes a little energy every step, but never adds any.
+        assert!(drift.max_energy_gain < 1e-9 * swing);
+        assert!(drift.max_energy_loss < 5e-2 * swing);
+        let spin = drift.initial.angular_momentum.z;
+        assert!((drift.latest.angular_momentum.z - spin).abs() < 5e-2 * spin.abs());
+    }
+
+    #[test]
+    fn resting_contact_comes_to_rest_without_gaining_energy() {
+        let mut world = scene::stack(3);
+        let mut drift = Drift::new(&world, Vector3::zero());
+        run(&mut world, &mut drift, 180);
+
+        assert!(drift.max_energy_gain < 1e-3 * drift.initial.energy());
+        assert!(drift.latest.kinetic_energy < 1e-6);
+        assert!(drift.latest.momentum.magnitude() < 1e-3);
+        // The cubes settle by the small gaps they were stacked with, but not into the ground.
+        assert!(drift.max_energy_loss < 1e-2 * drift.initial.energy());
+    }
+}
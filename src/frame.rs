@@ -1,9 +1,10 @@
-use cgmath::{Matrix4, Quaternion, Vector3, Zero};
+use cgmath::{InnerSpace, Matrix3, Matrix4, Quaternion, Vector3, VectorSpace, Zero};
 use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
 
 use crate::geometry::Plane;
 
-#[derive(Debug, Clone, Copy, Setters)]
+#[derive(Debug, Clone, Copy, Setters, Serialize, Deserialize)]
 pub struct Frame {
     pub position: Vector3<f64>,
     pub rotation: Quaternion<f64>,
@@ -19,12 +20,52 @@ impl Default for Frame {
 }
 
 impl Frame {
-    pub fn matrix(&self) -> Matrix4<f32> {
+    /// Constructs a frame at `eye` whose x-axis points towards `target`,
+    /// with the z-axis as close to `up` as possible.
+    /// If `target` coincides with `eye`, the frame looks along the x-axis.
+    /// If `up` is parallel to the view direction, the world axis least aligned with it is used instead.
+    pub fn look_at(eye: Vector3<f64>, target: Vector3<f64>, up: Vector3<f64>) -> Frame {
+        let forward = target - eye;
+        let forward = if forward.magnitude2() > 0.0 {
+            forward.normalize()
+        } else {
+            Vector3::unit_x()
+        };
+        let mut left = up.cross(forward);
+        if left.magnitude2() <= 1e-24 * up.magnitude2() {
+            let fallback = [Vector3::unit_z(), Vector3::unit_x(), Vector3::unit_y()]
+                .into_iter()
+                .min_by(|a, b| forward.dot(*a).abs().total_cmp(&forward.dot(*b).abs()))
+                .unwrap();
+            left = fallback.cross(forward);
+        }
+        let left = left.normalize();
+        let up = forward.cross(left);
+        Frame {
+            position: eye,
+            rotation: Matrix3::from_cols(forward, left, up).into(),
+        }
+    }
+
+    /// Extracts the frame from a rigid transformation matrix.
+    /// The upper 3x3 block is assumed to be orthonormal.
+    pub fn from_matrix(m: Matrix4<f64>) -> Frame {
+        Frame {
+            position: m.w.truncate(),
+            rotation: Matrix3::from_cols(m.x.truncate(), m.y.truncate(), m.z.truncate()).into(),
+        }
+    }
+
+    pub fn to_matrix_f64(&self) -> Matrix4<f64> {
         let mut m: Matrix4<f64> = self.rotation.into();
         m.w.x = self.position.x;
         m.w.y = self.position.y;
         m.w.z = self.position.z;
-        m.cast().unwrap()
+        m
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        self.to_matrix_f64().cast().unwrap()
     }
 
     pub fn inverse(&self) -> Frame {
@@ -36,9 +77,37 @@ impl Frame {
         }
     }
 
+    /// Interpolates between two frames, linearly in position and normalized linearly in rotation.
+    /// Cheaper than `slerp`, but the angular speed is not constant over the interpolation.
+    pub fn lerp(self, other: Frame, amount: f64) -> Frame {
+        Frame {
+            position: self.position.lerp(other.position, amount),
+            rotation: self.rotation.nlerp(other.rotation, amount),
+        }
+    }
+
+    /// Interpolates between two frames, linearly in position and spherically in rotation.
+    pub fn slerp(self, other: Frame, amount: f64) -> Frame {
+        Frame {
+            position: self.position.lerp(other.position, amount),
+            rotation: self.rotation.slerp(other.rotation, amount),
+        }
+    }
+
+    /// Transforms a direction, which is only rotated.
+    pub fn transform_direction(&self, direction: Vector3<f64>) -> Vector3<f64> {
+        self.rotation * direction
+    }
+
+    /// Transforms a point from the target space of the frame back into its source space.
+    /// Equivalent to `self.inverse() * point`.
+    pub fn inverse_transform_point(&self, point: Vector3<f64>) -> Vector3<f64> {
+        self.rotation.conjugate() * (point - self.position)
+    }
+
     /// Computes the position difference of a global point in the current frame from the same point in the past frame.
     pub fn delta(&self, past: Frame, global: Vector3<f64>) -> Vector3<f64> {
-        let local = self.inverse() * global;
+        let local = self.inverse_transform_point(global);
         let past_global = past * local;
         global - past_global
     }
@@ -74,3 +143,88 @@ impl std::ops::Mul for Frame {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, Euler, InnerSpace, Vector3, Zero};
+
+    use super::Frame;
+
+    fn frame() -> Frame {
+        Frame {
+            position: vec3(1.0, -2.0, 0.5),
+            rotation: Euler::new(Deg(30.0), Deg(-45.0), Deg(110.0)).into(),
+        }
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        let frame = frame();
+        let round_trip = Frame::from_matrix(frame.to_matrix_f64());
+        let point = vec3(0.3, 0.7, -1.1);
+
+        assert!((round_trip * point - frame * point).magnitude() < 1e-12);
+        assert!(
+            (round_trip.transform_direction(point) - frame.transform_direction(point)).magnitude()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn inverse_transform_undoes_transform() {
+        let frame = frame();
+        let point = vec3(0.3, 0.7, -1.1);
+
+        assert!((frame.inverse_transform_point(frame * point) - point).magnitude() < 1e-12);
+        assert!(
+            (frame.inverse_transform_point(point) - frame.inverse() * point).magnitude() < 1e-12
+        );
+    }
+
+    #[test]
+    fn look_at_points_x_axis_at_target() {
+        let target = vec3(4.0, 1.0, 2.0);
+        let frame = Frame::look_at(vec3(1.0, -1.0, 0.0), target, Vector3::unit_z());
+        let forward = frame.transform_direction(Vector3::unit_x());
+        let up = frame.transform_direction(Vector3::unit_z());
+
+        assert!((forward - (target - frame.position).normalize()).magnitude() < 1e-12);
+        assert!(up.z > 0.0 && up.dot(forward).abs() < 1e-12);
+        assert!(frame.transform_direction(Vector3::unit_y()).z.abs() < 1e-12);
+    }
+
+    #[test]
+    fn look_at_handles_degenerate_directions() {
+        let eye = vec3(1.0, 2.0, 3.0);
+        for (target, up) in [
+            (eye, Vector3::unit_z()),
+            (eye + Vector3::unit_z(), Vector3::unit_z()),
+            (eye - 2.0 * Vector3::unit_z(), Vector3::unit_z()),
+            (eye + Vector3::unit_x(), Vector3::zero()),
+        ] {
+            let frame = Frame::look_at(eye, target, up);
+            let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]
+                .map(|axis| frame.transform_direction(axis));
+            for (i, a) in axes.iter().enumerate() {
+                assert!((a.magnitude() - 1.0).abs() < 1e-12);
+                for b in &axes[i + 1..] {
+                    assert!(a.dot(*b).abs() < 1e-12);
+                }
+            }
+            if target != eye {
+                assert!((axes[0] - (target - eye).normalize()).magnitude() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn interpolation_hits_both_ends() {
+        let (a, b) = (Frame::default(), frame());
+        let point = vec3(0.3, 0.7, -1.1);
+
+        for interpolate in [Frame::lerp, Frame::slerp] {
+            assert!((interpolate(a, b, 0.0) * point - a * point).magnitude() < 1e-12);
+            assert!((interpolate(a, b, 1.0) * point - b * point).magnitude() < 1e-12);
+        }
+    }
+}
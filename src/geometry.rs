@@ -1,7 +1,14 @@
+pub mod compound;
+pub mod decomposition;
+pub mod heightfield;
+mod hull;
 pub mod integrate;
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use cgmath::{vec3, InnerSpace, Vector3, Zero};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 use crate::frame::Frame;
 
@@ -77,8 +84,44 @@ impl Default for Plane {
     }
 }
 
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Aabb {
+    /// The smallest box containing all points.
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f64>>) -> Aabb {
+        points.into_iter().fold(
+            Aabb {
+                min: vec3(f64::MAX, f64::MAX, f64::MAX),
+                max: vec3(f64::MIN, f64::MIN, f64::MIN),
+            },
+            |aabb, p| Aabb {
+                min: aabb.min.zip(p, f64::min),
+                max: aabb.max.zip(p, f64::max),
+            },
+        )
+    }
+
+    /// Enlarges the box by a margin in every direction.
+    pub fn expand(self, margin: f64) -> Aabb {
+        let margin = vec3(margin, margin, margin);
+        Aabb {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+}
+
 /// A convex polytope. The surface is assumed to form a manifold.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Polytope {
     pub vertices: Vec<Vector3<f64>>,
 
@@ -89,10 +132,89 @@ pub struct Polytope {
     /// Vertices sharing a face are assumed to be co-planar.
     pub faces: Vec<Vec<usize>>,
 
+    /// A point inside the polytope, against which face planes are oriented outwards.
+    /// `Polytope::new` places it at the average of all vertices, while generators centered at the origin place it there.
+    /// For pyramids and cones, that is the center of mass rather than the vertex average.
     pub centroid: Vector3<f64>,
 }
 
 impl Polytope {
+    /// Construct a polytope from its vertices and faces.
+    /// Edges are derived from the face boundaries and the centroid is the average of all vertices.
+    pub fn new(vertices: Vec<Vector3<f64>>, faces: Vec<Vec<usize>>) -> Self {
+        let edges = faces
+            .iter()
+            .flat_map(|face| face.iter().copied().circular_tuple_windows())
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let centroid = vertices.iter().sum::<Vector3<f64>>() / vertices.len() as f64;
+
+        Self {
+            vertices,
+            edges,
+            faces,
+            centroid,
+        }
+    }
+
+    /// The convex hull of a point cloud.
+    /// Co-planar hull triangles are merged into polygons.
+    /// Returns `None` if the points do not span a volume.
+    pub fn convex_hull(points: &[Vector3<f64>]) -> Option<Self> {
+        hull::convex_hull(points)
+    }
+
+    /// Checks that the faces bound a convex volume around the centroid.
+    /// Every face needs at least three vertices in range, which lie on its plane,
+    /// and no vertex may lie in front of any face.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self
+            .vertices
+            .iter()
+            .all(|v| v.x.is_finite() && v.y.is_finite() && v.z.is_finite())
+        {
+            return Err("Vertices must be finite".to_string());
+        }
+        if self.faces.iter().any(|face| face.len() < 3) {
+            return Err("Face has fewer than three vertices".to_string());
+        }
+        if self
+            .faces
+            .iter()
+            .flatten()
+            .any(|&index| index >= self.vertices.len())
+        {
+            return Err("Face index out of range".to_string());
+        }
+
+        let scale = self
+            .vertices
+            .iter()
+            .map(|v| v.magnitude())
+            .fold(0.0, f64::max);
+        let epsilon = 1e-9 * scale;
+        for (i, face) in self.faces.iter().enumerate() {
+            let plane = self.plane(i);
+            if !plane.normal.x.is_finite() || plane.distance(self.centroid) >= -epsilon {
+                return Err(format!(
+                    "Face {i} does not span a plane around the centroid"
+                ));
+            }
+            if face
+                .iter()
+                .any(|&v| plane.distance(self.vertices[v]).abs() > epsilon)
+            {
+                return Err(format!("Face {i} is not planar"));
+            }
+            if self.vertices.iter().any(|&v| plane.distance(v) > epsilon) {
+                return Err(format!("The polytope is not convex at face {i}"));
+            }
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn new_tetrahedron() -> Self {
         Self {
@@ -229,6 +351,176 @@ impl Polytope {
         }
     }
 
+    /// A box with the given half-extents, centered at the origin.
+    #[allow(dead_code)]
+    pub fn new_box(half_extents: Vector3<f64>) -> Self {
+        let mut cube = Self::new_cube();
+        for vertex in &mut cube.vertices {
+            *vertex = vec3(
+                (2.0 * vertex.x - 1.0) * half_extents.x,
+                (2.0 * vertex.y - 1.0) * half_extents.y,
+                (2.0 * vertex.z - 1.0) * half_extents.z,
+            );
+        }
+        cube.centroid = Vector3::zero();
+        cube
+    }
+
+    /// A prism extruding a regular polygon along the z-axis, centered at the origin.
+    /// The polygon's vertices lie on a circle with the given radius.
+    ///
+    /// Panics if there are less than three sides.
+    #[allow(dead_code)]
+    pub fn new_prism(sides: usize, radius: f64, half_height: f64) -> Self {
+        let polygon = regular_polygon(sides, radius);
+        let vertices = polygon
+            .iter()
+            .map(|v| v - half_height * Vector3::unit_z())
+            .chain(polygon.iter().map(|v| v + half_height * Vector3::unit_z()))
+            .collect();
+
+        let mut faces = vec![(0..sides).rev().collect(), (sides..2 * sides).collect()];
+        for i in 0..sides {
+            let j = (i + 1) % sides;
+            faces.push(vec![i, j, sides + j, sides + i]);
+        }
+
+        Self {
+            centroid: Vector3::zero(),
+            ..Self::new(vertices, faces)
+        }
+    }
+
+    /// A cylinder along the z-axis, approximated by a prism with the given number of segments.
+    ///
+    /// Panics if there are less than three segments.
+    #[allow(dead_code)]
+    pub fn new_cylinder(segments: usize, radius: f64, half_height: f64) -> Self {
+        Self::new_prism(segments, radius, half_height)
+    }
+
+    /// A pyramid over a regular polygon, with its apex pointing along the z-axis.
+    /// Like prisms, it is `2 * half_height` high.
+    /// The base lies a quarter of the height below the origin, which puts the center of mass at the origin.
+    ///
+    /// Panics if there are less than three sides.
+    #[allow(dead_code)]
+    pub fn new_pyramid(sides: usize, radius: f64, half_height: f64) -> Self {
+        let vertices = regular_polygon(sides, radius)
+            .into_iter()
+            .map(|v| v - 0.5 * half_height * Vector3::unit_z())
+            .chain([1.5 * half_height * Vector3::unit_z()])
+            .collect();
+
+        let mut faces = vec![(0..sides).rev().collect()];
+        for i in 0..sides {
+            faces.push(vec![i, (i + 1) % sides, sides]);
+        }
+
+        Self {
+            centroid: Vector3::zero(),
+            ..Self::new(vertices, faces)
+        }
+    }
+
+    /// A cone along the z-axis, approximated by a pyramid with the given number of segments.
+    ///
+    /// Panics if there are less than three segments.
+    #[allow(dead_code)]
+    pub fn new_cone(segments: usize, radius: f64, half_height: f64) -> Self {
+        Self::new_pyramid(segments, radius, half_height)
+    }
+
+    /// An octahedron with vertices lying on the unit sphere, centered at the origin.
+    #[allow(dead_code)]
+    pub fn new_octahedron() -> Self {
+        let vertices = vec![
+            vec3(1.0, 0.0, 0.0),
+            vec3(-1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, -1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, -1.0),
+        ];
+
+        // One face per octant, wound counter-clockwise when the octant is of even parity.
+        let mut faces = Vec::new();
+        for octant in 0_usize..8 {
+            let (x, y, z) = (octant & 1, 2 + (octant >> 1 & 1), 4 + (octant >> 2 & 1));
+            if octant.count_ones() % 2 == 0 {
+                faces.push(vec![x, y, z]);
+            } else {
+                faces.push(vec![x, z, y]);
+            }
+        }
+
+        Self {
+            centroid: Vector3::zero(),
+            ..Self::new(vertices, faces)
+        }
+    }
+
+    /// A dodecahedron with vertices lying on the unit sphere, centered at the origin.
+    #[allow(dead_code)]
+    pub fn new_dodecahedron() -> Self {
+        let phi = (1.0 + 5.0_f64.sqrt()) / 2.0;
+        let mut vertices = Vec::new();
+        for a in [-1.0, 1.0] {
+            for b in [-1.0, 1.0] {
+                vertices.push(vec3(0.0, a / phi, b * phi));
+                vertices.push(vec3(a / phi, b * phi, 0.0));
+                vertices.push(vec3(a * phi, 0.0, b / phi));
+                for c in [-1.0, 1.0] {
+                    vertices.push(vec3(a, b, c));
+                }
+            }
+        }
+        let vertices: Vec<_> = vertices.into_iter().map(|v| v / 3.0_f64.sqrt()).collect();
+
+        Self {
+            centroid: Vector3::zero(),
+            ..Self::convex_hull(&vertices).unwrap()
+        }
+    }
+
+    /// An icosahedron whose triangles are recursively subdivided into four,
+    /// with all vertices projected onto the unit sphere.
+    #[allow(dead_code)]
+    pub fn new_geodesic_sphere(subdivisions: usize) -> Self {
+        let icosahedron = Self::new_icosahedron();
+        let mut vertices = icosahedron.vertices;
+        let mut faces = icosahedron.faces;
+
+        for _ in 0..subdivisions {
+            let mut midpoints = BTreeMap::new();
+            let mut midpoint = |a: usize, b: usize| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    vertices.push((vertices[a] + vertices[b]).normalize());
+                    vertices.len() - 1
+                })
+            };
+
+            faces = faces
+                .iter()
+                .flat_map(|face| {
+                    let [a, b, c] = [face[0], face[1], face[2]];
+                    let [ab, bc, ca] = [midpoint(a, b), midpoint(b, c), midpoint(c, a)];
+                    [
+                        vec![a, ab, ca],
+                        vec![b, bc, ab],
+                        vec![c, ca, bc],
+                        vec![ab, bc, ca],
+                    ]
+                })
+                .collect();
+        }
+
+        Self {
+            centroid: Vector3::zero(),
+            ..Self::new(vertices, faces)
+        }
+    }
+
     pub fn face(&self, i: usize) -> impl Iterator<Item = Vector3<f64>> + '_ {
         self.faces[i].iter().map(|&i| self.vertices[i])
     }
@@ -291,21 +583,101 @@ impl Polytope {
     pub fn rigid_metrics(&self, density: f64) -> integrate::RigidMetrics {
         integrate::rigid_metrics(self, density)
     }
+
+    /// Maps every vertex and the centroid through a frame.
+    pub fn transformed(self, frame: Frame) -> Self {
+        Self {
+            vertices: self.vertices.into_iter().map(|v| frame * v).collect(),
+            centroid: frame * self.centroid,
+            ..self
+        }
+    }
+
+    pub fn translated(self, offset: Vector3<f64>) -> Self {
+        self.transformed(Frame {
+            position: offset,
+            ..Frame::default()
+        })
+    }
+
+    /// Scales each axis independently around the origin.
+    /// An odd number of negative factors mirrors the polytope, in which case the faces are re-oriented.
+    pub fn scaled(self, factors: Vector3<f64>) -> Self {
+        let scale = |v: Vector3<f64>| vec3(factors.x * v.x, factors.y * v.y, factors.z * v.z);
+        let mirrored = factors.x * factors.y * factors.z < 0.0;
+        Self {
+            vertices: self.vertices.into_iter().map(scale).collect(),
+            centroid: scale(self.centroid),
+            faces: if mirrored {
+                reverse_winding(self.faces)
+            } else {
+                self.faces
+            },
+            ..self
+        }
+    }
+
+    /// Reflects the polytope at the plane through the origin with the given normal.
+    pub fn mirrored(self, normal: Vector3<f64>) -> Self {
+        let normal = normal.normalize();
+        let reflect = |v: Vector3<f64>| v - 2.0 * v.dot(normal) * normal;
+        Self {
+            vertices: self.vertices.into_iter().map(reflect).collect(),
+            centroid: reflect(self.centroid),
+            faces: reverse_winding(self.faces),
+            ..self
+        }
+    }
+
+    /// Translates the polytope such that its centroid coincides with the origin.
+    pub fn recentered(self) -> Self {
+        let offset = -self.centroid;
+        self.translated(offset)
+    }
+
+    /// Translates the polytope such that its center of mass coincides with the origin.
+    /// The centroid is moved onto the center of mass as well.
+    pub fn recentered_at_center_of_mass(self) -> Self {
+        let offset = -self.rigid_metrics(1.0).center_of_mass;
+        Self {
+            centroid: Vector3::zero(),
+            ..self.translated(offset)
+        }
+    }
 }
 
+/// Scales uniformly around the origin.
+/// A negative factor point-reflects the polytope, which reverses the winding of its faces to keep them outward.
 impl std::ops::Mul<Polytope> for f64 {
     type Output = Polytope;
 
     fn mul(self, rhs: Polytope) -> Self::Output {
-        Polytope {
-            vertices: rhs.vertices.into_iter().map(|v| self * v).collect(),
-            edges: rhs.edges,
-            faces: rhs.faces,
-            centroid: self * rhs.centroid,
-        }
+        rhs.scaled(vec3(self, self, self))
     }
 }
 
+/// Flips the orientation of all faces by reversing their vertex order.
+fn reverse_winding(faces: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    faces
+        .into_iter()
+        .map(|face| face.into_iter().rev().collect())
+        .collect()
+}
+
+/// Vertices of a regular polygon in the xy-plane, in counter-clockwise order.
+fn regular_polygon(sides: usize, radius: f64) -> Vec<Vector3<f64>> {
+    assert!(
+        sides >= 3,
+        "A polygon needs at least three sides, got {sides}"
+    );
+    (0..sides)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / sides as f64;
+            radius * vec3(angle.cos(), angle.sin(), 0.0)
+        })
+        .collect()
+}
+
 trait CustomIterTools {
     type Item;
     fn total_max_by_key<T: TotalCmp, F: Fn(&Self::Item) -> T>(self, f: F) -> Option<Self::Item>;
@@ -333,3 +705,109 @@ impl TotalCmp for f64 {
         self.total_cmp(other)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, InnerSpace, Vector3, Zero};
+
+    use super::Polytope;
+
+    /// Checks that the winding of every face yields a normal pointing away from the centroid.
+    fn assert_outward(polytope: &Polytope) {
+        for face in &polytope.faces {
+            let [a, b, c] = [face[0], face[1], face[2]].map(|i| polytope.vertices[i]);
+            let normal = (b - a).cross(c - a);
+            assert!(normal.magnitude() > 1e-9);
+            for &vertex in face {
+                assert!(normal.dot(polytope.vertices[vertex] - polytope.centroid) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn generators_wind_faces_outwards_around_the_origin() {
+        let centered = [
+            Polytope::new_box(vec3(0.5, 0.3, 0.2)),
+            Polytope::new_prism(3, 0.7, 0.4),
+            Polytope::new_prism(8, 0.7, 0.4),
+            Polytope::new_cylinder(24, 0.5, 1.0),
+            Polytope::new_pyramid(4, 1.0, 1.0),
+            Polytope::new_cone(24, 0.5, 0.75),
+            Polytope::new_octahedron(),
+            Polytope::new_icosahedron(),
+            Polytope::new_dodecahedron(),
+            Polytope::new_geodesic_sphere(2),
+        ];
+        for polytope in &centered {
+            assert_eq!(polytope.centroid, Vector3::zero());
+            assert_outward(polytope);
+        }
+
+        assert_outward(&Polytope::new_cube());
+        assert_outward(&Polytope::new_tetrahedron());
+    }
+
+    #[test]
+    fn pyramids_are_centered_at_their_center_of_mass() {
+        let metrics = Polytope::new_pyramid(5, 1.0, 1.0).rigid_metrics(1.0);
+        assert!(metrics.center_of_mass.magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn reflections_keep_faces_outward() {
+        for polytope in [
+            Polytope::new_box(vec3(0.5, 0.3, 0.2)).translated(vec3(1.0, 2.0, 0.5)),
+            Polytope::new_pyramid(5, 1.0, 1.0).translated(vec3(-0.5, 0.0, 1.0)),
+        ] {
+            let volume = polytope.rigid_metrics(1.0).volume;
+            let centroid = polytope.centroid;
+
+            let mirrored = polytope.clone().mirrored(vec3(1.0, 1.0, 0.0));
+            assert_outward(&mirrored);
+            assert!(
+                (mirrored.centroid - vec3(-centroid.y, -centroid.x, centroid.z)).magnitude()
+                    < 1e-12
+            );
+            assert!((mirrored.rigid_metrics(1.0).volume - volume).abs() < 1e-12);
+
+            let scaled = polytope.clone().scaled(vec3(-2.0, 1.0, 0.5));
+            assert_outward(&scaled);
+            assert_eq!(
+                scaled.centroid,
+                vec3(-2.0 * centroid.x, centroid.y, 0.5 * centroid.z)
+            );
+            assert!((scaled.rigid_metrics(1.0).volume - volume).abs() < 1e-12);
+
+            let reflected = -2.0 * polytope.clone();
+            assert_outward(&reflected);
+            assert_eq!(reflected.centroid, -2.0 * centroid);
+            assert!((reflected.rigid_metrics(1.0).volume - 8.0 * volume).abs() < 1e-12);
+
+            let twice = polytope.clone().scaled(vec3(-1.0, -1.0, 1.0));
+            assert_eq!(twice.faces, polytope.faces);
+            assert_outward(&twice);
+        }
+    }
+
+    #[test]
+    fn recentering_moves_the_centroid_to_the_origin() {
+        let box_ = Polytope::new_box(vec3(0.5, 0.3, 0.2)).translated(vec3(1.0, -2.0, 0.5));
+        let recentered = box_.recentered();
+        assert!(recentered.centroid.magnitude() < 1e-12);
+        assert_outward(&recentered);
+
+        // Unlike the center of mass, the vertex average of a pyramid lies towards its base.
+        let pyramid = Polytope::new_pyramid(4, 1.0, 1.0).translated(vec3(0.0, 0.0, 3.0));
+        let pyramid = Polytope::new(pyramid.vertices, pyramid.faces);
+        let recentered = pyramid.recentered_at_center_of_mass();
+        assert_eq!(recentered.centroid, Vector3::zero());
+        assert!(recentered.rigid_metrics(1.0).center_of_mass.magnitude() < 1e-12);
+        assert_outward(&recentered);
+    }
+
+    #[test]
+    #[should_panic]
+    fn polygons_need_three_sides() {
+        Polytope::new_prism(2, 1.0, 1.0);
+    }
+}
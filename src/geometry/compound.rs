@@ -0,0 +1,130 @@
+use cgmath::{InnerSpace, Matrix, Matrix3, SquareMatrix, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    decomposition::{self, DecompositionParameters, TriangleMesh},
+    integrate::RigidMetrics,
+    Polytope,
+};
+use crate::frame::Frame;
+
+/// A shape composed of several convex polytopes, which allows concave rigid bodies.
+/// Parts are assumed not to overlap, otherwise the overlapping volume is accounted for twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Compound {
+    /// Convex parts along with frames transforming from part space to compound space.
+    pub parts: Vec<(Frame, Polytope)>,
+}
+
+impl Compound {
+    pub fn new(parts: Vec<(Frame, Polytope)>) -> Self {
+        Self { parts }
+    }
+
+    /// Approximates a concave mesh by a set of convex parts.
+    /// Returns `None` if the mesh encloses no volume.
+    pub fn decompose(mesh: &TriangleMesh, parameters: DecompositionParameters) -> Option<Self> {
+        decomposition::decompose(mesh, parameters)
+    }
+
+    /// An iterator over the vertices of all parts, in compound space.
+    pub fn vertices(&self) -> impl Iterator<Item = Vector3<f64>> + '_ {
+        self.parts
+            .iter()
+            .flat_map(|(frame, polytope)| polytope.vertices.iter().map(move |&v| *frame * v))
+    }
+
+    /// Combines the metrics of all parts.
+    /// The part inertia tensors are rotated into compound space
+    /// and shifted to the common center of mass by the parallel axis theorem.
+    /// Without any mass, the center of mass is placed at the origin.
+    pub fn rigid_metrics(&self, density: f64) -> RigidMetrics {
+        let parts: Vec<(Frame, RigidMetrics)> = self
+            .parts
+            .iter()
+            .map(|(frame, polytope)| (*frame, polytope.rigid_metrics(density)))
+            .collect();
+
+        let mass: f64 = parts.iter().map(|(_, metrics)| metrics.mass).sum();
+        let volume = parts.iter().map(|(_, metrics)| metrics.volume).sum();
+        let center_of_mass = if mass > 0.0 {
+            parts
+                .iter()
+                .map(|(frame, metrics)| metrics.mass * (*frame * metrics.center_of_mass))
+                .sum::<Vector3<f64>>()
+                / mass
+        } else {
+            Vector3::zero()
+        };
+
+        let mut inertia_tensor = Matrix3::zero();
+        for (frame, metrics) in &parts {
+            let rotation = Matrix3::from(frame.rotation);
+            let offset = *frame * metrics.center_of_mass - center_of_mass;
+            let outer = Matrix3::from_cols(offset * offset.x, offset * offset.y, offset * offset.z);
+
+            inertia_tensor += rotation * metrics.inertia_tensor * rotation.transpose();
+            inertia_tensor += metrics.mass * (offset.magnitude2() * Matrix3::identity() - outer);
+        }
+
+        RigidMetrics {
+            mass,
+            volume,
+            center_of_mass,
+            inertia_tensor,
+        }
+    }
+}
+
+impl From<Polytope> for Compound {
+    fn from(polytope: Polytope) -> Self {
+        Self {
+            parts: vec![(Frame::default(), polytope)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, InnerSpace, Vector3, Zero};
+
+    use super::Compound;
+    use crate::{frame::Frame, geometry::Polytope};
+
+    #[test]
+    fn offset_boxes_follow_the_parallel_axis_theorem() {
+        let compound = Compound::new(vec![
+            (
+                Frame::default().position(vec3(1.0, 0.0, 0.0)),
+                Polytope::new_box(vec3(0.5, 0.5, 0.5)),
+            ),
+            (
+                Frame::default().position(vec3(-1.0, 0.0, 1.0)),
+                Polytope::new_box(vec3(1.0, 0.5, 0.5)),
+            ),
+        ]);
+        let metrics = compound.rigid_metrics(1.0);
+
+        // A unit cube of mass one and a box of mass two, whose centers lie at
+        // (4/3, 0, -2/3) and (-2/3, 0, 1/3) relative to the common center of mass.
+        assert!((metrics.mass - 3.0).abs() < 1e-12);
+        assert!((metrics.center_of_mass - vec3(-1.0 / 3.0, 0.0, 2.0 / 3.0)).magnitude() < 1e-12);
+        let expected = [
+            [7.0 / 6.0, 0.0, 4.0 / 3.0],
+            [0.0, 13.0 / 3.0, 0.0],
+            [4.0 / 3.0, 0.0, 11.0 / 3.0],
+        ];
+        for (i, row) in expected.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                assert!((metrics.inertia_tensor[i][j] - value).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_compounds_have_no_mass() {
+        let metrics = Compound::new(Vec::new()).rigid_metrics(1.0);
+        assert_eq!(metrics.mass, 0.0);
+        assert_eq!(metrics.center_of_mass, Vector3::zero());
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::BTreeSet;
+
+use cgmath::{vec3, Vector3};
+use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
+
+use super::{compound::Compound, Polytope};
+use crate::frame::Frame;
+
+/// A closed triangle mesh which, unlike a `Polytope`, may be concave.
+#[derive(Debug, Clone, Default)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Vector3<f64>>,
+
+    /// Triangles indexing vertices.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+#[derive(Debug, Clone, Copy, Setters, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecompositionParameters {
+    /// Number of voxels along the longest side of the mesh's bounding box.
+    pub resolution: usize,
+
+    /// The fraction of a part's hull volume which may lie outside the mesh.
+    /// Parts exceeding it are split further.
+    pub concavity: f64,
+
+    /// Maximal number of nested splits.
+    pub depth: usize,
+}
+
+impl TriangleMesh {
+    /// Parses the vertices and faces of a Wavefront OBJ file.
+    /// Polygonal faces are triangulated as fans, all other statements are ignored.
+    pub fn from_obj(source: &str) -> Result<TriangleMesh, String> {
+        let mut mesh = TriangleMesh::default();
+        for (line_number, line) in source.lines().enumerate() {
+            let error = |message: &str| format!("Line {}: {message}", line_number + 1);
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coordinates = tokens
+                        .take(3)
+                        .map(str::parse)
+                        .collect::<Result<Vec<f64>, _>>()
+                        .map_err(|_| error("Invalid vertex coordinate"))?;
+                    if coordinates.len() < 3 {
+                        return Err(error("Vertex has fewer than three coordinates"));
+                    }
+                    mesh.vertices
+                        .push(vec3(coordinates[0], coordinates[1], coordinates[2]));
+                }
+                Some("f") => {
+                    // Indices are one-based, negative ones count back from the latest vertex.
+                    // Texture coordinate and normal indices following a slash are ignored.
+                    let count = mesh.vertices.len() as i64;
+                    let face = tokens
+                        .map(|token| {
+                            let index: i64 = token
+                                .split('/')
+                                .next()
+                                .unwrap()
+                                .parse()
+                                .map_err(|_| error("Invalid face index"))?;
+                            let index = if index < 0 { count + index } else { index - 1 };
+                            if (0..count).contains(&index) {
+                                Ok(index as usize)
+                            } else {
+                                Err(error("Face index out of range"))
+                            }
+                        })
+                        .collect::<Result<Vec<usize>, _>>()?;
+                    if face.len() < 3 {
+                        return Err(error("Face has fewer than three vertices"));
+                    }
+                    for i in 1..face.len() - 1 {
+                        mesh.triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+impl Default for DecompositionParameters {
+    fn default() -> Self {
+        Self {
+            resolution: 24,
+            concavity: 0.05,
+            depth: 5,
+        }
+    }
+}
+
+/// Number of cutting planes tried per axis when splitting a part.
+const CUTS: usize = 8;
+
+type Cell = [i64; 3];
+
+const NEIGHBORS: [Cell; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+struct Grid {
+    origin: Vector3<f64>,
+    size: f64,
+    dimensions: [i64; 3],
+}
+
+/// Voxelizes the mesh and recursively cuts the voxel set by axis-aligned planes, in the spirit of V-HACD.
+/// Every cut is chosen to minimize the combined hull volume of both halves.
+/// The resulting parts are the convex hulls of their voxels.
+/// Returns `None` if no voxel lies inside the mesh, e.g. because it is empty or not closed.
+pub fn decompose(mesh: &TriangleMesh, parameters: DecompositionParameters) -> Option<Compound> {
+    let grid = Grid::new(mesh, parameters.resolution);
+    let cells = grid.voxelize(mesh);
+
+    let mut parts = Vec::new();
+    for component in components(cells) {
+        split(&grid, component, parameters, parameters.depth, &mut parts);
+    }
+    if parts.is_empty() {
+        return None;
+    }
+
+    Some(Compound::new(
+        parts
+            .into_iter()
+            .map(|polytope| (Frame::default(), polytope))
+            .collect(),
+    ))
+}
+
+fn split(
+    grid: &Grid,
+    cells: Vec<Cell>,
+    parameters: DecompositionParameters,
+    depth: usize,
+    parts: &mut Vec<Polytope>,
+) {
+    let hull = match grid.hull(&cells) {
+        Some(hull) => hull,
+        None => return,
+    };
+
+    let concavity = 1.0 - grid.volume(&cells) / hull.rigid_metrics(1.0).volume;
+    if depth == 0 || concavity <= parameters.concavity {
+        parts.push(hull);
+        return;
+    }
+
+    let mut best: Option<(f64, Vec<Cell>, Vec<Cell>)> = None;
+    for axis in 0..3 {
+        let low = cells.iter().map(|cell| cell[axis]).min().unwrap();
+        let high = cells.iter().map(|cell| cell[axis]).max().unwrap();
+        let stride = ((high - low) as usize / CUTS).max(1);
+
+        for cut in (low + 1..=high).step_by(stride) {
+            let (left, right): (Vec<Cell>, Vec<Cell>) =
+                cells.iter().partition(|cell| cell[axis] < cut);
+            let cost = grid.hull_volume(&left) + grid.hull_volume(&right);
+            if best
+                .as_ref()
+                .is_none_or(|(best_cost, ..)| cost < *best_cost)
+            {
+                best = Some((cost, left, right));
+            }
+        }
+    }
+
+    match best {
+        Some((_, left, right)) => {
+            for half in [left, right] {
+                for component in components(half.into_iter().collect()) {
+                    split(grid, component, parameters, depth - 1, parts);
+                }
+            }
+        }
+        None => parts.push(hull),
+    }
+}
+
+/// Partitions cells into face-connected components.
+fn components(mut cells: BTreeSet<Cell>) -> Vec<Vec<Cell>> {
+    let mut components = Vec::new();
+    while let Some(seed) = cells.pop_first() {
+        let mut component = vec![seed];
+        let mut i = 0;
+        while i < component.len() {
+            let cell = component[i];
+            for offset in NEIGHBORS {
+                let neighbor = [0, 1, 2].map(|axis| cell[axis] + offset[axis]);
+                if cells.remove(&neighbor) {
+                    component.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+        components.push(component);
+    }
+    components
+}
+
+impl Grid {
+    fn new(mesh: &TriangleMesh, resolution: usize) -> Self {
+        let min = mesh
+            .vertices
+            .iter()
+            .fold(Vector3::new(f64::MAX, f64::MAX, f64::MAX), |min, v| {
+                vec3(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z))
+            });
+        let max = mesh
+            .vertices
+            .iter()
+            .fold(Vector3::new(f64::MIN, f64::MIN, f64::MIN), |max, v| {
+                vec3(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z))
+            });
+        let extent = max - min;
+        let size = extent.x.max(extent.y).max(extent.z) / resolution.max(1) as f64;
+
+        Self {
+            origin: min,
+            size,
+            dimensions: [0, 1, 2].map(|axis| ((extent[axis] / size).ceil() as i64).max(1)),
+        }
+    }
+
+    fn corner(&self, corner: Cell) -> Vector3<f64> {
+        self.origin + self.size * vec3(corner[0] as f64, corner[1] as f64, corner[2] as f64)
+    }
+
+    /// Fills all cells whose center lies inside the mesh.
+    /// Casts one ray per row of cells along the x-axis and fills the cells between pairs of crossings.
+    fn voxelize(&self, mesh: &TriangleMesh) -> BTreeSet<Cell> {
+        let mut cells = BTreeSet::new();
+
+        for j in 0..self.dimensions[1] {
+            for k in 0..self.dimensions[2] {
+                // Nudge the ray off the grid to avoid hitting mesh edges exactly.
+                let y = self.origin.y + self.size * (j as f64 + 0.5 + 1.2345e-5);
+                let z = self.origin.z + self.size * (k as f64 + 0.5 + 2.3456e-5);
+
+                let mut crossings: Vec<f64> = mesh
+                    .triangles
+                    .iter()
+                    .filter_map(|triangle| {
+                        let [a, b, c] = triangle.map(|i| mesh.vertices[i]);
+                        let determinant = (b.y - c.y) * (a.z - c.z) + (c.z - b.z) * (a.y - c.y);
+                        if determinant == 0.0 {
+                            return None;
+                        }
+                        let u = ((b.y - c.y) * (z - c.z) + (c.z - b.z) * (y - c.y)) / determinant;
+                        let v = ((c.y - a.y) * (z - c.z) + (a.z - c.z) * (y - c.y)) / determinant;
+                        let w = 1.0 - u - v;
+                        (u >= 0.0 && v >= 0.0 && w >= 0.0).then_some(u * a.x + v * b.x + w * c.x)
+                    })
+                    .collect();
+                crossings.sort_by(f64::total_cmp);
+
+                for span in crossings.chunks_exact(2) {
+                    for i in 0..self.dimensions[0] {
+                        let x = self.origin.x + self.size * (i as f64 + 0.5);
+                        if span[0] <= x && x <= span[1] {
+                            cells.insert([i, j, k]);
+                        }
+                    }
+                }
+            }
+        }
+
+        cells
+    }
+
+    fn volume(&self, cells: &[Cell]) -> f64 {
+        cells.len() as f64 * self.size.powi(3)
+    }
+
+    /// The convex hull of the given cells.
+    /// Only the corners of cells on the boundary of the set can contribute to it.
+    fn hull(&self, cells: &[Cell]) -> Option<Polytope> {
+        let set: BTreeSet<Cell> = cells.iter().copied().collect();
+        let mut corners = BTreeSet::new();
+
+        for cell in cells {
+            let boundary = NEIGHBORS
+                .iter()
+                .any(|offset| !set.contains(&[0, 1, 2].map(|axis| cell[axis] + offset[axis])));
+            if !boundary {
+                continue;
+            }
+            for corner in 0..8 {
+                corners.insert([0, 1, 2].map(|axis| cell[axis] + (corner >> axis & 1)));
+            }
+        }
+
+        let points: Vec<Vector3<f64>> = corners.into_iter().map(|c| self.corner(c)).collect();
+        Polytope::convex_hull(&points)
+    }
+
+    fn hull_volume(&self, cells: &[Cell]) -> f64 {
+        self.hull(cells)
+            .map_or(0.0, |hull| hull.rigid_metrics(1.0).volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompose, DecompositionParameters, TriangleMesh};
+
+    /// An L-shaped profile in the xz plane, extruded by one along y, with a volume of three.
+    /// The front and back faces are fans around the reflex corner, so that they triangulate correctly.
+    const BRACKET: &str = "\
+v 1 0 1
+v 1 0 2
+v 0 0 2
+v 0 0 0
+v 2 0 0
+v 2 0 1
+v 1 1 1
+v 1 1 2
+v 0 1 2
+v 0 1 0
+v 2 1 0
+v 2 1 1
+f 1 2 3 4 5 6
+f 12 11 10 9 8 7
+f 1 7 8 2
+f 2 8 9 3
+f 3 9 10 4
+f 4 10 11 5
+f 5 11 12 6
+f 6 12 7 1
+";
+
+    #[test]
+    fn bracket_parts_add_up_to_the_mesh_volume() {
+        let mesh = TriangleMesh::from_obj(BRACKET).unwrap();
+        let compound = decompose(&mesh, DecompositionParameters::default()).unwrap();
+
+        assert!(compound.parts.len() >= 2);
+        let volume: f64 = compound
+            .parts
+            .iter()
+            .map(|(_, part)| part.rigid_metrics(1.0).volume)
+            .sum();
+        assert!((volume - 3.0).abs() < 0.05 * 3.0, "{volume}");
+    }
+
+    #[test]
+    fn empty_meshes_have_no_parts() {
+        let mesh = TriangleMesh::default();
+        assert!(decompose(&mesh, DecompositionParameters::default()).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+use cgmath::{vec3, Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+
+use super::{Aabb, Plane};
+
+/// Terrain fixed in the world, given by heights on a regular grid in the xy plane.
+/// Every cell is split into two triangles along its diagonal from `(i + 1, j)` to `(i, j + 1)`.
+/// Everything below the surface is solid, so rigids entering from the sides get pushed up onto it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heightfield {
+    /// The xy position of the first grid point.
+    pub origin: Vector2<f64>,
+
+    /// Distance between adjacent grid points in `m`.
+    pub spacing: f64,
+
+    /// Number of grid points along x.
+    pub columns: usize,
+
+    /// Heights of the grid points in `m`, row by row along x.
+    pub heights: Vec<f64>,
+}
+
+impl Heightfield {
+    /// Samples heights from a function of the xy position.
+    pub fn from_fn(
+        origin: Vector2<f64>,
+        spacing: f64,
+        (columns, rows): (usize, usize),
+        height: impl Fn(f64, f64) -> f64,
+    ) -> Heightfield {
+        let heights = (0..rows)
+            .flat_map(|j| (0..columns).map(move |i| (i, j)))
+            .map(|(i, j)| height(origin.x + i as f64 * spacing, origin.y + j as f64 * spacing))
+            .collect();
+        Heightfield {
+            origin,
+            spacing,
+            columns,
+            heights,
+        }
+    }
+
+    /// Checks that the grid has at least one cell and a positive spacing.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.spacing > 0.0 && self.spacing.is_finite()) {
+            return Err(format!("Spacing must be positive, got {}", self.spacing));
+        }
+        if self.columns < 2 || self.heights.len() % self.columns != 0 || self.rows() < 2 {
+            return Err(format!(
+                "{} heights do not form a grid of at least two rows with {} columns",
+                self.heights.len(),
+                self.columns
+            ));
+        }
+        if !self.heights.iter().all(|height| height.is_finite()) {
+            return Err("Heights must be finite".to_string());
+        }
+        Ok(())
+    }
+
+    /// Number of grid points along y.
+    pub fn rows(&self) -> usize {
+        self.heights.len() / self.columns.max(1)
+    }
+
+    /// The world space position of a grid point.
+    pub fn point(&self, i: usize, j: usize) -> Vector3<f64> {
+        vec3(
+            self.origin.x + i as f64 * self.spacing,
+            self.origin.y + j as f64 * self.spacing,
+            self.heights[j * self.columns + i],
+        )
+    }
+
+    /// The plane of the triangle above or below an xy position, with its normal pointing upwards.
+    /// Returns `None` outside of the grid.
+    pub fn plane(&self, x: f64, y: f64) -> Option<Plane> {
+        let u = (x - self.origin.x) / self.spacing;
+        let v = (y - self.origin.y) / self.spacing;
+        if !(u >= 0.0 && v >= 0.0) {
+            return None;
+        }
+
+        let (i, j) = (u.floor() as usize, v.floor() as usize);
+        if i + 1 >= self.columns || j + 1 >= self.rows() {
+            return None;
+        }
+
+        let corners = if u.fract() + v.fract() <= 1.0 {
+            [self.point(i, j), self.point(i + 1, j), self.point(i, j + 1)]
+        } else {
+            [
+                self.point(i + 1, j + 1),
+                self.point(i, j + 1),
+                self.point(i + 1, j),
+            ]
+        };
+        Some(Plane::from_points(corners))
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let (columns, rows) = (self.columns, self.rows());
+        Aabb::from_points((0..rows).flat_map(|j| (0..columns).map(move |i| self.point(i, j))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec2, vec3, InnerSpace};
+
+    use super::Heightfield;
+    use crate::{debug::DebugLines, scene};
+
+    #[test]
+    fn planes_interpolate_between_grid_points() {
+        let heightfield =
+            Heightfield::from_fn(vec2(-1.0, 2.0), 0.5, (4, 3), |x, y| 0.3 * x - 0.2 * y + 1.0);
+
+        let plane = heightfield.plane(-0.2, 2.7).unwrap();
+        assert!(plane.normal.z > 0.0);
+        assert!((plane.normal.magnitude() - 1.0).abs() < 1e-12);
+        for (x, y) in [(-0.2, 2.7), (0.4, 2.1), (-1.0, 2.0)] {
+            let height = 0.3 * x - 0.2 * y + 1.0;
+            let plane = heightfield.plane(x, y).unwrap();
+            assert!(plane.distance(vec3(x, y, height)).abs() < 1e-12);
+        }
+
+        assert!(heightfield.plane(-1.1, 2.5).is_none());
+        assert!(heightfield.plane(0.6, 2.5).is_none());
+        assert!(heightfield.plane(0.0, 3.1).is_none());
+    }
+
+    #[test]
+    fn malformed_grids_are_rejected() {
+        let heightfield = Heightfield::from_fn(vec2(0.0, 0.0), 0.5, (4, 3), |_, _| 0.0);
+        assert!(heightfield.validate().is_ok());
+
+        for invalid in [
+            Heightfield {
+                spacing: 0.0,
+                ..heightfield.clone()
+            },
+            Heightfield {
+                spacing: f64::NAN,
+                ..heightfield.clone()
+            },
+            Heightfield {
+                columns: 0,
+                ..heightfield.clone()
+            },
+            Heightfield {
+                columns: 5,
+                ..heightfield.clone()
+            },
+            Heightfield {
+                heights: vec![0.0; 4],
+                ..heightfield.clone()
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn cubes_come_to_rest_on_the_surface() {
+        let mut world = scene::terrain(16);
+        for _ in 0..240 {
+            world.integrate(1.0 / 60.0, &mut DebugLines::default());
+        }
+
+        let terrain = world.terrain.as_ref().unwrap();
+        for rigid in &world.rigids {
+            assert!(rigid.asleep);
+            let lowest = world.shapes[rigid.shape]
+                .vertices()
+                .map(|vertex| rigid.frame() * vertex)
+                .map(|vertex| {
+                    let plane = terrain.plane(vertex.x, vertex.y).unwrap();
+                    plane.distance(vertex)
+                })
+                .fold(f64::MAX, f64::min);
+            assert!(lowest.abs() < 1e-3);
+        }
+    }
+}
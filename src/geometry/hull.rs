@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use cgmath::{InnerSpace, Vector3};
+
+use super::{Plane, Polytope};
+
+struct Face {
+    vertices: [usize; 3],
+    plane: Plane,
+    outside: Vec<usize>,
+}
+
+impl Face {
+    fn new(points: &[Vector3<f64>], vertices: [usize; 3], interior: Vector3<f64>) -> Face {
+        let mut vertices = vertices;
+        let mut plane = Plane::from_points(vertices.map(|i| points[i]));
+        if plane.distance(interior) > 0.0 {
+            vertices.swap(1, 2);
+            plane = plane.flip();
+        }
+        Face {
+            vertices,
+            plane,
+            outside: Vec::new(),
+        }
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Incrementally grows a hull from an initial simplex, as laid out in Gregorius' QuickHull talk.
+/// Points within `epsilon` of a face are treated as lying on it.
+pub fn convex_hull(points: &[Vector3<f64>]) -> Option<Polytope> {
+    let scale: f64 = (0..3)
+        .map(|axis| points.iter().map(|p| p[axis].abs()).fold(0.0, f64::max))
+        .sum();
+    let epsilon = 1e-9 * scale;
+
+    let simplex = initial_simplex(points, epsilon)?;
+    let interior = simplex.iter().map(|&i| points[i]).sum::<Vector3<f64>>() / 4.0;
+
+    let mut faces: Vec<Face> = [[0, 1, 2], [0, 3, 1], [1, 3, 2], [2, 3, 0]]
+        .into_iter()
+        .map(|face| Face::new(points, face.map(|i| simplex[i]), interior))
+        .collect();
+
+    let candidates = (0..points.len()).filter(|i| !simplex.contains(i)).collect();
+    assign(points, &mut faces, candidates, epsilon);
+
+    while let Some(eye) = faces
+        .iter()
+        .find(|face| !face.outside.is_empty())
+        .map(|face| furthest(points, face))
+    {
+        let visible: Vec<bool> = faces
+            .iter()
+            .map(|face| face.plane.distance(points[eye]) > epsilon)
+            .collect();
+
+        let visible_edges: BTreeSet<(usize, usize)> = faces
+            .iter()
+            .zip(&visible)
+            .filter(|(_, &visible)| visible)
+            .flat_map(|(face, _)| face.edges())
+            .collect();
+
+        let horizon = visible_edges
+            .iter()
+            .filter(|&&(a, b)| !visible_edges.contains(&(b, a)));
+
+        let new_faces: Vec<Face> = horizon
+            .map(|&(a, b)| Face::new(points, [a, b, eye], interior))
+            .collect();
+
+        let mut orphans = Vec::new();
+        let mut i = 0;
+        faces.retain_mut(|face| {
+            let keep = !visible[i];
+            i += 1;
+            if !keep {
+                orphans.extend(face.outside.drain(..).filter(|&p| p != eye));
+            }
+            keep
+        });
+
+        let first_new_face = faces.len();
+        faces.extend(new_faces);
+        assign(points, &mut faces[first_new_face..], orphans, epsilon);
+    }
+
+    build(points, &faces, epsilon)
+}
+
+fn initial_simplex(points: &[Vector3<f64>], epsilon: f64) -> Option<[usize; 4]> {
+    let extremes = |axis: usize| {
+        let order = |a: &usize, b: &usize| points[*a][axis].total_cmp(&points[*b][axis]);
+        Some((
+            (0..points.len()).min_by(order)?,
+            (0..points.len()).max_by(order)?,
+        ))
+    };
+    let (i0, i1) = (0..3).filter_map(extremes).max_by(|a, b| {
+        let extent = |&(min, max): &(usize, usize)| (points[max] - points[min]).magnitude2();
+        extent(a).total_cmp(&extent(b))
+    })?;
+
+    let line = points[i1] - points[i0];
+    let i2 = (0..points.len()).max_by(|&a, &b| {
+        let distance = |i: usize| line.cross(points[i] - points[i0]).magnitude2();
+        distance(a).total_cmp(&distance(b))
+    })?;
+
+    let normal = line.cross(points[i2] - points[i0]);
+    let i3 = (0..points.len()).max_by(|&a, &b| {
+        let distance = |i: usize| normal.dot(points[i] - points[i0]).abs();
+        distance(a).total_cmp(&distance(b))
+    })?;
+
+    if line.magnitude() <= epsilon
+        || normal.magnitude() <= epsilon * line.magnitude()
+        || normal.normalize().dot(points[i3] - points[i0]).abs() <= epsilon
+    {
+        return None;
+    }
+
+    Some([i0, i1, i2, i3])
+}
+
+/// Hands each point to the first face it lies in front of.
+/// Points behind all faces are inside the hull and get discarded.
+fn assign(points: &[Vector3<f64>], faces: &mut [Face], candidates: Vec<usize>, epsilon: f64) {
+    for point in candidates {
+        if let Some(face) = faces
+            .iter_mut()
+            .find(|face| face.plane.distance(points[point]) > epsilon)
+        {
+            face.outside.push(point);
+        }
+    }
+}
+
+fn furthest(points: &[Vector3<f64>], face: &Face) -> usize {
+    face.outside
+        .iter()
+        .copied()
+        .max_by(|&a, &b| {
+            face.plane
+                .distance(points[a])
+                .total_cmp(&face.plane.distance(points[b]))
+        })
+        .unwrap()
+}
+
+/// Merges co-planar triangles into polygons and compacts the vertex list.
+/// Returns `None` if inconsistently classified points left the boundary of a polygon open.
+fn build(points: &[Vector3<f64>], faces: &[Face], epsilon: f64) -> Option<Polytope> {
+    let mut adjacent = BTreeMap::new();
+    for (i, face) in faces.iter().enumerate() {
+        for edge in face.edges() {
+            adjacent.insert(edge, i);
+        }
+    }
+
+    let mut merged = vec![false; faces.len()];
+    let mut polygons = Vec::new();
+
+    for seed in 0..faces.len() {
+        if merged[seed] {
+            continue;
+        }
+        merged[seed] = true;
+
+        let plane = faces[seed].plane;
+        let mut group = vec![seed];
+        let mut i = 0;
+        while i < group.len() {
+            for (a, b) in faces[group[i]].edges() {
+                // Points close to co-planar may be classified inconsistently, leaving an edge without twin.
+                let Some(&neighbor) = adjacent.get(&(b, a)) else {
+                    continue;
+                };
+                // Slivers along the edges of the hull can be tilted arbitrarily, so the vertices decide.
+                let coplanar = faces[neighbor].plane.normal.dot(plane.normal) > 0.0
+                    && faces[neighbor]
+                        .vertices
+                        .iter()
+                        .all(|&v| plane.distance(points[v]).abs() <= epsilon);
+                if !merged[neighbor] && coplanar {
+                    merged[neighbor] = true;
+                    group.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+
+        let edges: BTreeSet<(usize, usize)> =
+            group.iter().flat_map(|&face| faces[face].edges()).collect();
+        let successors: BTreeMap<usize, usize> = edges
+            .iter()
+            .copied()
+            .filter(|&(a, b)| !edges.contains(&(b, a)))
+            .collect();
+
+        let start = *successors.keys().next()?;
+        let mut boundary = vec![start];
+        let mut vertex = successors[&start];
+        while vertex != start {
+            if boundary.len() > successors.len() {
+                return None;
+            }
+            boundary.push(vertex);
+            vertex = *successors.get(&vertex)?;
+        }
+
+        // Drop vertices in the middle of straight boundary runs, so that any three polygon vertices span the plane.
+        let n = boundary.len();
+        let polygon: Vec<usize> = (0..n)
+            .filter(|&i| {
+                let previous = points[boundary[(i + n - 1) % n]];
+                let current = points[boundary[i]];
+                let next = points[boundary[(i + 1) % n]];
+                (current - previous).cross(next - current).magnitude()
+                    > epsilon * (next - previous).magnitude()
+            })
+            .map(|i| boundary[i])
+            .collect();
+
+        // Slivers which were not merged into a neighbor have no area.
+        if polygon.len() >= 3 {
+            polygons.push(polygon);
+        }
+    }
+
+    let used: BTreeSet<usize> = polygons.iter().flatten().copied().collect();
+    let remap: BTreeMap<usize, usize> = used.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+
+    Some(Polytope::new(
+        used.iter().map(|&v| points[v]).collect(),
+        polygons
+            .into_iter()
+            .map(|polygon| polygon.into_iter().map(|v| remap[&v]).collect())
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Vector3};
+
+    use super::convex_hull;
+
+    #[test]
+    fn coplanar_faces_are_merged_and_inner_points_dropped() {
+        let mut points: Vec<Vector3<f64>> = (0..27)
+            .map(|i| vec3((i % 3) as f64, (i / 3 % 3) as f64, (i / 9) as f64) / 2.0)
+            .collect();
+        points.push(vec3(0.3, 0.6, 0.2));
+        points.push(vec3(0.25, 0.0, 0.75));
+
+        let hull = convex_hull(&points).unwrap();
+        assert_eq!(hull.vertices.len(), 8);
+        assert_eq!(hull.faces.len(), 6);
+        assert!(hull.faces.iter().all(|face| face.len() == 4));
+        assert!((hull.rigid_metrics(1.0).volume - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn nearly_coplanar_points_are_tolerated() {
+        // Points jittered by less than the tolerance of the hull.
+        let points: Vec<Vector3<f64>> = (0..125)
+            .map(|i| {
+                let jitter = 1e-10 * ((i * 7919 % 13) as f64 - 6.0);
+                vec3((i % 5) as f64, (i / 5 % 5) as f64, (i / 25) as f64) / 4.0
+                    + vec3(jitter, -jitter, jitter)
+            })
+            .collect();
+
+        let hull = convex_hull(&points).unwrap();
+        assert!((hull.rigid_metrics(1.0).volume - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn flat_point_sets_have_no_hull() {
+        let points = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 1.0, 0.0),
+        ];
+        assert!(convex_hull(&points).is_none());
+    }
+}
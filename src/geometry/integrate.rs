@@ -1,6 +1,8 @@
-use cgmath::{Matrix3, Vector3, Zero};
+use std::fmt;
 
-use super::Polytope;
+use cgmath::{InnerSpace, Matrix, Matrix3, Quaternion, SquareMatrix, Vector3, Zero};
+
+use super::{Aabb, Polytope};
 
 #[derive(Default, Debug)]
 struct Polyhedron {
@@ -23,6 +25,65 @@ pub struct RigidMetrics {
     pub inertia_tensor: Matrix3<f64>,
 }
 
+/// Reasons why metrics cannot describe a rigid body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsError {
+    /// The polytope encloses no volume, e.g. because all of its vertices are co-planar.
+    ZeroVolume,
+
+    /// The mass is not positive, e.g. because of a non-positive density.
+    NonPositiveMass,
+
+    /// A principal moment of inertia is not positive, so the inertia tensor cannot be inverted.
+    SingularInertia,
+}
+
+impl fmt::Display for MetricsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetricsError::ZeroVolume => write!(f, "Shape encloses no volume"),
+            MetricsError::NonPositiveMass => write!(f, "Mass is not positive"),
+            MetricsError::SingularInertia => write!(f, "Inertia tensor is not invertible"),
+        }
+    }
+}
+
+impl std::error::Error for MetricsError {}
+
+impl RigidMetrics {
+    /// Checks that the metrics describe a body which can be simulated.
+    pub fn validate(&self) -> Result<(), MetricsError> {
+        if !(self.volume > 0.0 && self.volume.is_finite()) {
+            return Err(MetricsError::ZeroVolume);
+        }
+        if !(self.mass > 0.0 && self.mass.is_finite()) {
+            return Err(MetricsError::NonPositiveMass);
+        }
+        let (moments, _) = self.principal_axes();
+        if !(0..3).all(|i| moments[i] > 0.0 && moments[i].is_finite()) {
+            return Err(MetricsError::SingularInertia);
+        }
+        Ok(())
+    }
+
+    /// Diagonalizes the inertia tensor.
+    /// Returns the principal moments of inertia, together with the rotation
+    /// from principal space, in which the tensor is diagonal, to object space.
+    pub fn principal_axes(&self) -> (Vector3<f64>, Quaternion<f64>) {
+        let (moments, mut axes) = diagonalize(self.inertia_tensor);
+
+        // Keep the axes right-handed, so that they form a rotation.
+        if axes.determinant() < 0.0 {
+            axes.z = -axes.z;
+        }
+
+        (moments, Quaternion::from(axes))
+    }
+}
+
+/// Computes mass, volume, center of mass and inertia tensor about the center of mass.
+/// Faces may be wound either way, since they are re-oriented to agree with their outward planes.
+/// Polytopes without volume result in zero metrics, which are rejected by [`RigidMetrics::validate`].
 pub fn rigid_metrics(polytope: &Polytope, density: f64) -> RigidMetrics {
     let mut polyhedron = Polyhedron {
         vertices: polytope.vertices.clone(),
@@ -30,15 +91,34 @@ pub fn rigid_metrics(polytope: &Polytope, density: f64) -> RigidMetrics {
     };
 
     for (i, plane) in polytope.planes().enumerate() {
+        // The projection integrals assume counter-clockwise vertices around the normal.
+        let mut vertices = polytope.faces[i].clone();
+        let [a, b, c] = [vertices[0], vertices[1], vertices[2]].map(|i| polytope.vertices[i]);
+        if (b - a).cross(c - a).dot(plane.normal) < 0.0 {
+            vertices.reverse();
+        }
+
         polyhedron.faces.push(Face {
             normal: plane.normal,
             displacement: plane.constant(),
-            vertices: polytope.faces[i].clone(),
+            vertices,
         });
     }
 
     let integrals = volume_integrals(&polyhedron);
 
+    // Flat polytopes leave nothing but rounding errors behind.
+    let bounds = Aabb::from_points(polytope.vertices.iter().copied());
+    let size = (bounds.max - bounds.min).magnitude();
+    if integrals.t0.is_nan() || integrals.t0 <= 1e-12 * size.powi(3) {
+        return RigidMetrics {
+            mass: 0.0,
+            volume: 0.0,
+            center_of_mass: polytope.centroid,
+            inertia_tensor: Matrix3::zero(),
+        };
+    }
+
     let m = density * integrals.t0;
     let r = integrals.t1 / integrals.t0;
 
@@ -287,6 +367,43 @@ fn projection_integrals(
     integrals
 }
 
+/// Computes eigenvalues and eigenvectors of a symmetric matrix with the cyclic Jacobi method.
+/// The eigenvectors are returned as the columns of an orthonormal matrix.
+fn diagonalize(matrix: Matrix3<f64>) -> (Vector3<f64>, Matrix3<f64>) {
+    let mut a = matrix;
+    let mut v = Matrix3::identity();
+
+    for _ in 0..32 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)]
+            .into_iter()
+            .max_by(|&(p0, q0), &(p1, q1)| a[q0][p0].abs().total_cmp(&a[q1][p1].abs()))
+            .unwrap();
+
+        let diagonal = a.x.x.abs() + a.y.y.abs() + a.z.z.abs();
+        if a[q][p].abs() <= f64::EPSILON * diagonal {
+            break;
+        }
+
+        // Rotate in the `pq`-plane such that the `pq` entry vanishes.
+        let theta = (a[q][q] - a[p][p]) / (2.0 * a[q][p]);
+        let t =
+            if theta >= 0.0 { 1.0 } else { -1.0 } / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let c = 1.0 / (t * t + 1.0).sqrt();
+        let s = t * c;
+
+        let mut rotation = Matrix3::identity();
+        rotation[p][p] = c;
+        rotation[q][q] = c;
+        rotation[q][p] = s;
+        rotation[p][q] = -s;
+
+        a = rotation.transpose() * a * rotation;
+        v = v * rotation;
+    }
+
+    (Vector3::new(a.x.x, a.y.y, a.z.z), v)
+}
+
 fn sq(x: f64) -> f64 {
     x.powi(2)
 }
@@ -294,3 +411,200 @@ fn sq(x: f64) -> f64 {
 fn cb(x: f64) -> f64 {
     x.powi(3)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, SQRT_2};
+
+    use cgmath::{vec3, Deg, Euler, Matrix, Matrix3, SquareMatrix, Vector3, Zero};
+
+    use super::{MetricsError, RigidMetrics};
+    use crate::{frame::Frame, geometry::Polytope, rigid::Rigid};
+
+    fn assert_close(actual: RigidMetrics, expected: RigidMetrics) {
+        let scale = expected.mass.max(1.0);
+        assert!((actual.mass - expected.mass).abs() < 1e-12 * scale);
+        assert!((actual.volume - expected.volume).abs() < 1e-12 * scale);
+        for i in 0..3 {
+            assert!((actual.center_of_mass[i] - expected.center_of_mass[i]).abs() < 1e-12);
+            for j in 0..3 {
+                let error = actual.inertia_tensor[i][j] - expected.inertia_tensor[i][j];
+                assert!(error.abs() < 1e-12 * scale, "{actual:?} != {expected:?}");
+            }
+        }
+    }
+
+    /// Metrics of a solid of the given density whose inertia tensor is diagonal in object space.
+    fn diagonal(
+        density: f64,
+        volume: f64,
+        center_of_mass: Vector3<f64>,
+        moments: Vector3<f64>,
+    ) -> RigidMetrics {
+        let mass = density * volume;
+        RigidMetrics {
+            mass,
+            volume,
+            center_of_mass,
+            inertia_tensor: Matrix3::from_diagonal(mass * moments),
+        }
+    }
+
+    #[test]
+    fn cube_matches_closed_form() {
+        let expected = diagonal(2.0, 1.0, vec3(0.5, 0.5, 0.5), vec3(1.0, 1.0, 1.0) / 6.0);
+        assert_close(Polytope::new_cube().rigid_metrics(2.0), expected);
+    }
+
+    #[test]
+    fn box_matches_closed_form() {
+        let (a, b, c) = (0.5, 0.3, 0.2);
+        let expected = diagonal(
+            3.0,
+            8.0 * a * b * c,
+            Vector3::zero(),
+            vec3(b * b + c * c, c * c + a * a, a * a + b * b) / 3.0,
+        );
+        assert_close(
+            Polytope::new_box(vec3(a, b, c)).rigid_metrics(3.0),
+            expected,
+        );
+    }
+
+    #[test]
+    fn corner_tetrahedron_has_products_of_inertia() {
+        // The corner tetrahedron has products of inertia, unlike the other solids.
+        let mass = 1.0 / 6.0;
+        let mut inertia_tensor = Matrix3::from_diagonal(vec3(2.0, 2.0, 2.0) * 3.0 * mass / 80.0);
+        for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+            inertia_tensor[i][j] = mass / 80.0;
+            inertia_tensor[j][i] = mass / 80.0;
+        }
+        let expected = RigidMetrics {
+            mass,
+            volume: 1.0 / 6.0,
+            center_of_mass: vec3(0.25, 0.25, 0.25),
+            inertia_tensor,
+        };
+        assert_close(Polytope::new_tetrahedron().rigid_metrics(1.0), expected);
+    }
+
+    #[test]
+    fn prism_matches_closed_form() {
+        let (sides, radius, half_height) = (6, 0.7, 0.4);
+        let angle = 2.0 * PI / sides as f64;
+        let area = sides as f64 * radius * radius * angle.sin() / 2.0;
+        let polar = radius * radius * (2.0 + angle.cos()) / 6.0;
+        let expected = diagonal(
+            1.0,
+            2.0 * half_height * area,
+            Vector3::zero(),
+            vec3(
+                polar / 2.0 + half_height * half_height / 3.0,
+                polar / 2.0 + half_height * half_height / 3.0,
+                polar,
+            ),
+        );
+        assert_close(
+            Polytope::new_prism(sides, radius, half_height).rigid_metrics(1.0),
+            expected,
+        );
+    }
+
+    #[test]
+    fn icosahedron_matches_closed_form() {
+        let sqrt_5 = 5.0_f64.sqrt();
+        let edge = 4.0 / (10.0 + 2.0 * sqrt_5).sqrt();
+        let moment = edge * edge * (3.0 + sqrt_5) / 20.0;
+        let expected = diagonal(
+            1.0,
+            5.0 * (3.0 + sqrt_5) * edge.powi(3) / 12.0,
+            Vector3::zero(),
+            vec3(moment, moment, moment),
+        );
+        assert_close(Polytope::new_icosahedron().rigid_metrics(1.0), expected);
+    }
+
+    #[test]
+    fn transformed_copies_move_along() {
+        let frame = Frame {
+            position: vec3(3.0, -1.0, SQRT_2),
+            rotation: Euler::new(Deg(20.0), Deg(-35.0), Deg(50.0)).into(),
+        };
+        let rotation = Matrix3::from(frame.rotation);
+
+        for polytope in [
+            Polytope::new_box(vec3(0.5, 0.3, 0.2)),
+            Polytope::new_tetrahedron(),
+            Polytope::new_prism(5, 0.7, 0.4),
+            Polytope::new_icosahedron(),
+        ] {
+            let metrics = polytope.rigid_metrics(1.5);
+            let expected = RigidMetrics {
+                center_of_mass: frame * metrics.center_of_mass,
+                inertia_tensor: rotation * metrics.inertia_tensor * rotation.transpose(),
+                ..metrics
+            };
+            assert_close(polytope.transformed(frame).rigid_metrics(1.5), expected);
+        }
+    }
+
+    #[test]
+    fn winding_does_not_matter() {
+        for polytope in [
+            Polytope::new_cube(),
+            Polytope::new_tetrahedron(),
+            Polytope::new_prism(7, 0.5, 1.0),
+            Polytope::new_icosahedron(),
+        ] {
+            let expected = polytope.rigid_metrics(1.0);
+
+            let mut flipped = polytope.clone();
+            for face in &mut flipped.faces {
+                face.reverse();
+            }
+            assert_close(flipped.rigid_metrics(1.0), expected);
+
+            let mut mixed = polytope;
+            for face in mixed.faces.iter_mut().step_by(2) {
+                face.reverse();
+            }
+            assert_close(mixed.rigid_metrics(1.0), expected);
+        }
+    }
+
+    #[test]
+    fn degenerate_metrics_are_rejected() {
+        let square = Polytope::new(
+            vec![
+                vec3(0.0, 0.0, 0.0),
+                vec3(1.0, 0.0, 0.0),
+                vec3(1.0, 1.0, 0.0),
+                vec3(0.0, 1.0, 0.0),
+            ],
+            vec![vec![0, 1, 2, 3], vec![3, 2, 1, 0]],
+        );
+        assert_eq!(square.rigid_metrics(1.0).volume, 0.0);
+        assert_eq!(
+            Rigid::new(square.rigid_metrics(1.0)).err(),
+            Some(MetricsError::ZeroVolume)
+        );
+
+        let cube = Polytope::new_cube();
+        assert_eq!(
+            Rigid::new(cube.rigid_metrics(0.0)).err(),
+            Some(MetricsError::NonPositiveMass)
+        );
+
+        let needle = RigidMetrics {
+            inertia_tensor: Matrix3::from_diagonal(vec3(1.0, 1.0, 0.0)),
+            ..cube.rigid_metrics(1.0)
+        };
+        assert_eq!(
+            Rigid::new(needle).err(),
+            Some(MetricsError::SingularInertia)
+        );
+
+        assert!(Rigid::new(cube.rigid_metrics(1.0)).is_ok());
+    }
+}
@@ -0,0 +1,355 @@
+//! Exports a recorded simulation as a glTF 2.0 animation, for playback in other tools.
+//! Every rigid becomes a node with translation and rotation channels sampled once per step.
+
+use std::{fs, io, path::Path, sync::Arc};
+
+use cgmath::InnerSpace;
+use serde_json::{json, Value};
+
+use crate::{frame::Frame, geometry::compound::Compound, world::World};
+
+/// Color of rigids without one, as in the viewer.
+const DEFAULT_COLOR: [f32; 3] = [0.4; 3];
+
+const FLOAT: u32 = 5126;
+const ARRAY_BUFFER: u32 = 34962;
+
+/// Frames of all rigids, recorded step by step.
+/// Rigids added after the first step are not animated.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    /// Duration of a step in `s`.
+    dt: f64,
+
+    shapes: Vec<Arc<Compound>>,
+
+    /// Shape and color of every rigid.
+    rigids: Vec<(usize, [f32; 3])>,
+
+    frames: Vec<Vec<Frame>>,
+}
+
+impl Animation {
+    /// Starts an animation at the given world.
+    pub fn new(world: &World, dt: f64) -> Animation {
+        let mut animation = Animation {
+            dt,
+            shapes: world.shapes.clone(),
+            rigids: world
+                .rigids
+                .iter()
+                .map(|rigid| (rigid.shape, rigid.color.unwrap_or(DEFAULT_COLOR)))
+                .collect(),
+            frames: Vec::new(),
+        };
+        animation.record(world);
+        animation
+    }
+
+    /// Samples the frames of all rigids as the next step.
+    pub fn record(&mut self, world: &World) {
+        self.frames.push(
+            world
+                .rigids
+                .iter()
+                .take(self.rigids.len())
+                .map(|rigid| rigid.frame())
+                .collect(),
+        );
+    }
+
+    /// Saves as binary `.glb`, or as `.gltf` with the buffer embedded.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("glb") => fs::write(path, self.to_glb()),
+            Some("gltf") => fs::write(path, self.to_gltf()),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Expected a .glb or .gltf file",
+            )),
+        }
+    }
+
+    /// The glTF document with its buffer embedded as a base64 data URI.
+    pub fn to_gltf(&self) -> String {
+        let (mut document, buffer) = self.document();
+        document["buffers"][0]["uri"] =
+            format!("data:application/octet-stream;base64,{}", base64(&buffer)).into();
+        document.to_string()
+    }
+
+    /// The glTF document in the binary container format.
+    pub fn to_glb(&self) -> Vec<u8> {
+        let (document, mut buffer) = self.document();
+        let mut json = document.to_string().into_bytes();
+
+        // Chunks are aligned to four bytes, JSON is padded with spaces and binary data with zeros.
+        json.resize(json.len().next_multiple_of(4), b' ');
+        buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+        let length = 12 + 8 + json.len() + 8 + buffer.len();
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        for (kind, chunk) in [(b"JSON", json), (b"BIN\0", buffer)] {
+            glb.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            glb.extend_from_slice(kind);
+            glb.extend_from_slice(&chunk);
+        }
+        glb
+    }
+
+    /// Builds the document along with its single buffer, whose URI is left for the container to fill in.
+    fn document(&self) -> (Value, Vec<u8>) {
+        let mut buffer = Buffer::default();
+
+        // Every rigid gets a mesh of its own to carry its color, but meshes of the same shape share their accessors.
+        let geometry: Vec<(usize, usize)> = self
+            .shapes
+            .iter()
+            .map(|shape| {
+                let (positions, normals) = triangles(shape);
+                (
+                    buffer.push(&positions, "VEC3", true, true),
+                    buffer.push(&normals, "VEC3", true, false),
+                )
+            })
+            .collect();
+
+        let times: Vec<[f32; 1]> = (0..self.frames.len())
+            .map(|step| [(step as f64 * self.dt) as f32])
+            .collect();
+        let time = buffer.push(&times, "SCALAR", false, true);
+
+        let mut meshes = Vec::new();
+        let mut materials = Vec::new();
+        let mut nodes = vec![json!({
+            // glTF is y-up, while the simulation is z-up.
+            "name": "World",
+            "rotation": [-std::f32::consts::FRAC_1_SQRT_2, 0.0, 0.0, std::f32::consts::FRAC_1_SQRT_2],
+            "children": (1..=self.rigids.len()).collect::<Vec<_>>(),
+        })];
+        let mut samplers = Vec::new();
+        let mut channels = Vec::new();
+
+        for (i, &(shape, color)) in self.rigids.iter().enumerate() {
+            let (positions, normals) = geometry[shape];
+            materials.push(json!({
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [color[0], color[1], color[2], 1.0],
+                    "metallicFactor": 0.0,
+                },
+            }));
+            meshes.push(json!({
+                "primitives": [{
+                    "attributes": { "POSITION": positions, "NORMAL": normals },
+                    "material": i,
+                }],
+            }));
+
+            let frames = self.frames.iter().map(|frames| frames[i]);
+            let translations: Vec<[f32; 3]> = frames
+                .clone()
+                .map(|frame| frame.position.cast().unwrap().into())
+                .collect();
+            // Quaternions of opposite sign describe the same rotation,
+            // but interpolating between them would take the long way round.
+            let mut rotations: Vec<[f32; 4]> = Vec::with_capacity(self.frames.len());
+            for frame in frames {
+                let mut q = frame.rotation.cast::<f32>().unwrap();
+                if let Some(&[x, y, z, s]) = rotations.last() {
+                    if q.s * s + q.v.x * x + q.v.y * y + q.v.z * z < 0.0 {
+                        q = -q;
+                    }
+                }
+                rotations.push([q.v.x, q.v.y, q.v.z, q.s]);
+            }
+
+            nodes.push(json!({
+                "name": format!("Rigid {i}"),
+                "mesh": i,
+                "translation": translations[0],
+                "rotation": rotations[0],
+            }));
+
+            for (path, output) in [
+                (
+                    "translation",
+                    buffer.push(&translations, "VEC3", false, false),
+                ),
+                ("rotation", buffer.push(&rotations, "VEC4", false, false)),
+            ] {
+                channels.push(json!({
+                    "sampler": samplers.len(),
+                    "target": { "node": i + 1, "path": path },
+                }));
+                samplers.push(json!({
+                    "input": time,
+                    "output": output,
+                    "interpolation": "LINEAR",
+                }));
+            }
+        }
+
+        let document = json!({
+            "asset": { "version": "2.0", "generator": "constraint_solver" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0] }],
+            "nodes": nodes,
+            "meshes": meshes,
+            "materials": materials,
+            "animations": [{ "name": "Simulation", "samplers": samplers, "channels": channels }],
+            "accessors": buffer.accessors,
+            "bufferViews": buffer.views,
+            "buffers": [{ "byteLength": buffer.data.len() }],
+        });
+        (document, buffer.data)
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    data: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Buffer {
+    /// Appends float elements with a view and an accessor of their own, and returns the accessor index.
+    /// Only vertex attributes may be bound to a vertex buffer,
+    /// and bounds are required for positions and animation inputs.
+    fn push<const N: usize>(
+        &mut self,
+        elements: &[[f32; N]],
+        kind: &str,
+        vertex: bool,
+        bounds: bool,
+    ) -> usize {
+        let offset = self.data.len();
+        for value in elements.iter().flatten() {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.data.len() - offset,
+        });
+        if vertex {
+            view["target"] = ARRAY_BUFFER.into();
+        }
+        self.views.push(view);
+
+        let mut accessor = json!({
+            "bufferView": self.views.len() - 1,
+            "componentType": FLOAT,
+            "count": elements.len(),
+            "type": kind,
+        });
+        if bounds {
+            let mut min = [f32::MAX; N];
+            let mut max = [f32::MIN; N];
+            for element in elements {
+                for i in 0..N {
+                    min[i] = min[i].min(element[i]);
+                    max[i] = max[i].max(element[i]);
+                }
+            }
+            accessor["min"] = json!(min.to_vec());
+            accessor["max"] = json!(max.to_vec());
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+}
+
+/// Flat shaded triangles of all parts, as separate vertices with face normals.
+/// The triangles are wound counter-clockwise when seen from outside, degenerate ones are left out.
+fn triangles(compound: &Compound) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    for (frame, polytope) in &compound.parts {
+        for (a, b, c) in polytope.triangles() {
+            let [a, b, c] = [a, b, c].map(|i| polytope.vertices[i]);
+            let normal = (b - a).cross(c - a);
+            if normal.magnitude2() == 0.0 {
+                continue;
+            }
+
+            // Polytopes are convex, so their faces point away from the centroid.
+            let (corners, normal) = if normal.dot(a - polytope.centroid) >= 0.0 {
+                ([a, b, c], normal)
+            } else {
+                ([a, c, b], -normal)
+            };
+            let normal = frame.rotation * normal.normalize();
+            for corner in corners {
+                positions.push((*frame * corner).cast::<f32>().unwrap().into());
+                normals.push(normal.cast::<f32>().unwrap().into());
+            }
+        }
+    }
+    (positions, normals)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, Animation};
+    use crate::{debug::DebugLines, world::World};
+
+    #[test]
+    fn base64_pads_incomplete_groups() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0x00]), "//79AA==");
+    }
+
+    #[test]
+    fn glb_contains_a_channel_pair_per_rigid() {
+        let mut world = World::new();
+        let mut animation = Animation::new(&world, 1.0 / 60.0);
+        for _ in 0..10 {
+            world.integrate(1.0 / 60.0, &mut DebugLines::default());
+            animation.record(&world);
+        }
+        let glb = animation.to_glb();
+
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+
+        let length = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        assert_eq!(&glb[16..20], b"JSON");
+        let document: serde_json::Value = serde_json::from_slice(&glb[20..20 + length]).unwrap();
+        let channels = document["animations"][0]["channels"].as_array().unwrap();
+        assert_eq!(channels.len(), 2 * world.rigids.len());
+
+        let time = &document["accessors"][document["animations"][0]["samplers"][0]["input"]
+            .as_u64()
+            .unwrap() as usize];
+        assert_eq!(time["count"], 11);
+        assert_eq!(time["max"][0], (10.0f64 / 60.0) as f32 as f64);
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::VecDeque, mem::size_of, sync::Arc};
+
+use derive_setters::Setters;
+
+use crate::{
+    constraint::Constraint, debug::DebugLines, geometry::compound::Compound, rigid::Rigid,
+    world::World,
+};
+
+/// Records the course of a simulation with bounded memory.
+/// Only every `interval`-th world is kept as a keyframe.
+/// Frames in between are restored by re-simulating from the preceding keyframe,
+/// which reproduces them exactly since the simulation is deterministic.
+#[derive(Debug, Clone, Setters)]
+pub struct History {
+    /// Number of steps between keyframes.
+    /// Larger intervals save memory, but make seeking slower.
+    pub interval: usize,
+
+    /// Upper bound of the memory occupied by keyframes in bytes.
+    /// The oldest keyframes are discarded once it is exceeded, which makes their frames unreachable.
+    pub budget: usize,
+
+    #[setters(skip)]
+    dt: f64,
+
+    /// Keyframes along with their frame numbers, oldest first.
+    #[setters(skip)]
+    keyframes: VecDeque<(usize, World)>,
+
+    #[setters(skip)]
+    latest: (usize, World),
+
+    /// The most recently sought frame, so that seeking forward step by step does not start over from a keyframe.
+    #[setters(skip)]
+    cursor: Option<(usize, World)>,
+}
+
+impl History {
+    pub fn new(world: World, dt: f64) -> History {
+        History {
+            interval: 60,
+            budget: 256 << 20,
+            dt,
+            keyframes: VecDeque::from([(0, world.clone())]),
+            latest: (0, world),
+            cursor: None,
+        }
+    }
+
+    /// The most recent frame.
+    pub fn latest(&self) -> &World {
+        &self.latest.1
+    }
+
+    pub fn last_frame(&self) -> usize {
+        self.latest.0
+    }
+
+    /// The earliest frame which can still be sought.
+    pub fn first_frame(&self) -> usize {
+        self.keyframes[0].0
+    }
+
+    /// Memory occupied by keyframes in bytes.
+    /// Shapes are shared between all worlds and therefore not accounted for.
+    pub fn footprint(&self) -> usize {
+        self.keyframes
+            .iter()
+            .map(|(_, world)| footprint(world))
+            .sum()
+    }
+
+    /// Simulates the step following the latest frame.
+    pub fn advance(&mut self, debug: &mut DebugLines) {
+        let (frame, world) = &mut self.latest;
+        world.integrate(self.dt, debug);
+        *frame += 1;
+
+        if *frame % self.interval.max(1) == 0 {
+            self.keyframes.push_back((*frame, world.clone()));
+            while self.keyframes.len() > 1 && self.footprint() > self.budget {
+                self.keyframes.pop_front();
+            }
+        }
+    }
+
+    /// Restores a past frame, or returns `None` if it is not recorded.
+    pub fn seek(&mut self, frame: usize) -> Option<&World> {
+        if frame == self.latest.0 {
+            return Some(&self.latest.1);
+        }
+        if frame < self.first_frame() || frame > self.latest.0 {
+            return None;
+        }
+
+        let keyframe = self
+            .keyframes
+            .iter()
+            .rev()
+            .find(|(keyframe, _)| *keyframe <= frame)
+            .unwrap();
+        let (mut current, mut world) = match self.cursor.take() {
+            Some((cursor, world)) if keyframe.0 <= cursor && cursor <= frame => (cursor, world),
+            _ => keyframe.clone(),
+        };
+
+        while current < frame {
+            world.integrate(self.dt, &mut DebugLines::default());
+            current += 1;
+        }
+
+        Some(&self.cursor.insert((current, world)).1)
+    }
+}
+
+/// Memory owned by a world, without the shapes and the terrain, which are shared between all keyframes.
+fn footprint(world: &World) -> usize {
+    size_of::<World>()
+        + world.rigids.capacity() * size_of::<Rigid>()
+        + (world.joints.capacity() + world.contacts.capacity()) * size_of::<Constraint>()
+        + world.shapes.capacity() * size_of::<Arc<Compound>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem::size_of, sync::Arc};
+
+    use super::{footprint, History};
+    use crate::{debug::DebugLines, scene, world::World};
+
+    #[test]
+    fn sought_frames_match_the_recording() {
+        let mut history = History::new(World::new(), 1.0 / 60.0).interval(8);
+        let mut hashes = vec![history.latest().hash()];
+        for _ in 0..100 {
+            history.advance(&mut DebugLines::default());
+            hashes.push(history.latest().hash());
+        }
+
+        for frame in [37, 38, 39, 100, 0, 64, 63, 9, 8, 7] {
+            assert_eq!(history.seek(frame).unwrap().hash(), hashes[frame]);
+        }
+        assert!(history.seek(101).is_none());
+    }
+
+    #[test]
+    fn budget_discards_the_oldest_keyframes() {
+        let world = World::new();
+        let budget = 4 * super::footprint(&world.clone());
+        let mut history = History::new(world, 1.0 / 60.0).interval(10).budget(budget);
+        for _ in 0..100 {
+            history.advance(&mut DebugLines::default());
+        }
+
+        // Keyframes grow once contacts arise, so fewer than four of them fit.
+        let first_frame = history.first_frame();
+        assert!(history.footprint() <= budget);
+        assert!((70..100).contains(&first_frame));
+        assert_eq!(first_frame % 10, 0);
+        assert!(history.seek(first_frame - 1).is_none());
+        assert!(history.seek(first_frame + 5).is_some());
+    }
+
+    #[test]
+    fn footprint_includes_contacts_but_not_the_shared_terrain() {
+        let mut world = scene::terrain(16);
+        let empty = footprint(&world);
+        let heights = world.terrain.as_ref().unwrap().heights.len();
+        assert!(empty < heights * size_of::<f64>());
+
+        for _ in 0..60 {
+            world.integrate(1.0 / 60.0, &mut DebugLines::default());
+        }
+        assert!(!world.contacts.is_empty());
+        assert!(footprint(&world) > empty);
+
+        let copy = world.clone();
+        assert!(Arc::ptr_eq(
+            copy.terrain.as_ref().unwrap(),
+            world.terrain.as_ref().unwrap()
+        ));
+    }
+}
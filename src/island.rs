@@ -0,0 +1,61 @@
+use crate::rigid::Rigid;
+
+/// Partitions the elements `0..count` into the connected components of a graph, using union-find.
+/// Islands are ordered by their smallest element and list their elements in ascending order,
+/// so that the result does not depend on the order of the edges.
+pub fn islands(count: usize, edges: impl IntoIterator<Item = (usize, usize)>) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..count).collect();
+
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    for (a, b) in edges {
+        let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+        parents[a.max(b)] = a.min(b);
+    }
+
+    let mut islands: Vec<Vec<usize>> = Vec::new();
+    let mut island_of_root = vec![usize::MAX; count];
+    for i in 0..count {
+        let root = root(&mut parents, i);
+        if island_of_root[root] == usize::MAX {
+            island_of_root[root] = islands.len();
+            islands.push(Vec::new());
+        }
+        islands[island_of_root[root]].push(i);
+    }
+
+    islands
+}
+
+/// Wakes a sleeping rigid along with all other rigids of its island.
+pub fn wake(rigids: &mut [Rigid], index: usize) {
+    if !rigids[index].asleep {
+        return;
+    }
+    let island = rigids[index].island;
+    for rigid in rigids {
+        if rigid.asleep && rigid.island == island {
+            rigid.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::islands;
+
+    #[test]
+    fn islands_do_not_depend_on_edge_order() {
+        let edges = [(4, 1), (2, 0), (1, 5), (0, 3)];
+        let expected = vec![vec![0, 2, 3], vec![1, 4, 5], vec![6]];
+
+        assert_eq!(islands(7, edges), expected);
+        assert_eq!(islands(7, edges.into_iter().rev()), expected);
+    }
+}
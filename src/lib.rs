@@ -0,0 +1,25 @@
+#![allow(unused)]
+
+#[cfg(feature = "render")]
+pub mod app;
+#[cfg(feature = "render")]
+pub mod camera;
+pub mod collision;
+pub mod constraint;
+pub mod debug;
+pub mod diagnostics;
+pub mod frame;
+pub mod geometry;
+pub mod gltf;
+pub mod history;
+mod island;
+#[cfg(feature = "render")]
+pub mod mesh;
+pub mod recorder;
+#[cfg(feature = "render")]
+pub mod renderer;
+pub mod rigid;
+pub mod scene;
+pub mod snapshot;
+pub mod solver;
+pub mod world;
@@ -1,17 +1,4 @@
-#![allow(unused)]
-
-mod app;
-mod camera;
-mod collision;
-mod constraint;
-mod debug;
-mod frame;
-mod geometry;
-mod mesh;
-mod renderer;
-mod rigid;
-mod solver;
-mod world;
+use constraint_solver::app;
 
 #[async_std::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -1,4 +1,4 @@
-use crate::{frame::Frame, geometry, renderer};
+use crate::{frame::Frame, geometry, geometry::compound::Compound, renderer};
 use cgmath::{Matrix4, Vector3};
 use derive_setters::Setters;
 use wgpu::util::DeviceExt;
@@ -94,4 +94,16 @@ impl Mesh {
         }
         Mesh::from_vertices(renderer, &positions)
     }
+
+    pub fn from_compound(renderer: &renderer::Renderer, compound: &Compound) -> Self {
+        let mut positions = Vec::new();
+        for (frame, polytope) in &compound.parts {
+            for triangle in polytope.triangles() {
+                positions.push((*frame * polytope.vertices[triangle.0]).cast().unwrap());
+                positions.push((*frame * polytope.vertices[triangle.1]).cast().unwrap());
+                positions.push((*frame * polytope.vertices[triangle.2]).cast().unwrap());
+            }
+        }
+        Mesh::from_vertices(renderer, &positions)
+    }
 }
@@ -0,0 +1,232 @@
+//! Logs the state of every rigid per step, for analysis in other tools.
+//!
+//! CSV files start with a row of column names.
+//! Binary files start with a header describing the columns, followed by one fixed-size record per rigid and step,
+//! all little-endian, so that they can be read e.g. as a structured array:
+//!
+//! - The magic bytes `TRAJ` and the format version as `u16`.
+//! - The number of columns as `u16`.
+//! - Per column, its type as a byte, `u` for `u32` or `f` for `f64`,
+//!   followed by the length of its name as a byte and the name itself.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::world::World;
+
+pub const VERSION: u16 = 1;
+
+/// Column names along with their binary type.
+/// Rotations are quaternions, contacts count the contacts of the last substep, which sleeping rigids have none of.
+pub const COLUMNS: [(&str, u8); 19] = [
+    ("step", b'u'),
+    ("time", b'f'),
+    ("rigid", b'u'),
+    ("px", b'f'),
+    ("py", b'f'),
+    ("pz", b'f'),
+    ("qs", b'f'),
+    ("qx", b'f'),
+    ("qy", b'f'),
+    ("qz", b'f'),
+    ("vx", b'f'),
+    ("vy", b'f'),
+    ("vz", b'f'),
+    ("wx", b'f'),
+    ("wy", b'f'),
+    ("wz", b'f'),
+    ("kinetic", b'f'),
+    ("potential", b'f'),
+    ("contacts", b'u'),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Binary,
+}
+
+impl Format {
+    /// Tells the format by the file extension, `.csv` or `.bin`.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "bin" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Value {
+    Integer(u32),
+    Float(f64),
+}
+
+pub struct Recorder<W: Write> {
+    out: W,
+    format: Format,
+
+    /// Duration of a step in `s`.
+    dt: f64,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Creates a file, telling the format by its extension.
+    pub fn create(path: &Path, dt: f64) -> io::Result<Self> {
+        let format = Format::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Expected a .csv or .bin trajectory file",
+            )
+        })?;
+        Recorder::new(BufWriter::new(File::create(path)?), format, dt)
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Writes the header.
+    pub fn new(mut out: W, format: Format, dt: f64) -> io::Result<Self> {
+        match format {
+            Format::Csv => {
+                let names: Vec<&str> = COLUMNS.iter().map(|(name, _)| *name).collect();
+                writeln!(out, "{}", names.join(","))?;
+            }
+            Format::Binary => {
+                out.write_all(b"TRAJ")?;
+                out.write_all(&VERSION.to_le_bytes())?;
+                out.write_all(&(COLUMNS.len() as u16).to_le_bytes())?;
+                for (name, kind) in COLUMNS {
+                    out.write_all(&[kind, name.len() as u8])?;
+                    out.write_all(name.as_bytes())?;
+                }
+            }
+        }
+        Ok(Recorder { out, format, dt })
+    }
+
+    /// Logs all rigids of the world as it is after the given step.
+    pub fn record(&mut self, step: usize, world: &World) -> io::Result<()> {
+        let mut contacts = vec![0; world.rigids.len()];
+        for contact in &world.contacts {
+            contacts[contact.rigids.0] += 1;
+            if let Some(rigid) = contact.rigids.1 {
+                contacts[rigid] += 1;
+            }
+        }
+
+        for (i, rigid) in world.rigids.iter().enumerate() {
+            let (p, q, v, w) = (
+                rigid.position,
+                rigid.rotation,
+                rigid.velocity,
+                rigid.angular_velocity,
+            );
+            let row = [
+                Value::Integer(step as u32),
+                Value::Float(step as f64 * self.dt),
+                Value::Integer(i as u32),
+                Value::Float(p.x),
+                Value::Float(p.y),
+                Value::Float(p.z),
+                Value::Float(q.s),
+                Value::Float(q.v.x),
+                Value::Float(q.v.y),
+                Value::Float(q.v.z),
+                Value::Float(v.x),
+                Value::Float(v.y),
+                Value::Float(v.z),
+                Value::Float(w.x),
+                Value::Float(w.y),
+                Value::Float(w.z),
+                Value::Float(rigid.kinetic_energy()),
+                Value::Float(rigid.potential_energy(world.gravity)),
+                Value::Integer(contacts[i]),
+            ];
+            self.write(&row)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, row: &[Value]) -> io::Result<()> {
+        match self.format {
+            Format::Csv => {
+                for (i, value) in row.iter().enumerate() {
+                    if i > 0 {
+                        write!(self.out, ",")?;
+                    }
+                    match value {
+                        Value::Integer(value) => write!(self.out, "{value}")?,
+                        Value::Float(value) => write!(self.out, "{value}")?,
+                    }
+                }
+                writeln!(self.out)
+            }
+            Format::Binary => {
+                for value in row {
+                    match value {
+                        Value::Integer(value) => self.out.write_all(&value.to_le_bytes())?,
+                        Value::Float(value) => self.out.write_all(&value.to_le_bytes())?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, Recorder, COLUMNS};
+    use crate::{debug::DebugLines, scene};
+
+    fn record(format: Format) -> Vec<u8> {
+        let mut world = scene::stack(3);
+        let mut recorder = Recorder::new(Vec::new(), format, 1.0 / 60.0).unwrap();
+        for step in 1..=30 {
+            world.integrate(1.0 / 60.0, &mut DebugLines::default());
+            recorder.record(step, &world).unwrap();
+        }
+        recorder.finish().unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_row_per_rigid_and_step() {
+        let csv = String::from_utf8(record(Format::Csv)).unwrap();
+        let rows: Vec<Vec<&str>> = csv.lines().map(|line| line.split(',').collect()).collect();
+
+        assert_eq!(rows.len(), 1 + 30 * 3);
+        assert!(rows.iter().all(|row| row.len() == COLUMNS.len()));
+        assert_eq!(rows[0][0], "step");
+
+        // The bottom cube rests on the ground and carries the middle one.
+        let last = &rows[rows.len() - 3];
+        assert_eq!(last[..3], ["30", "0.5", "0"]);
+        assert!(last[18].parse::<usize>().unwrap() >= 8);
+    }
+
+    #[test]
+    fn binary_records_have_a_fixed_size() {
+        let binary = record(Format::Binary);
+        let header = 8 + COLUMNS
+            .iter()
+            .map(|(name, _)| 2 + name.len())
+            .sum::<usize>();
+        let size = COLUMNS
+            .iter()
+            .map(|(_, kind)| if *kind == b'u' { 4 } else { 8 })
+            .sum::<usize>();
+
+        assert_eq!(&binary[..4], b"TRAJ");
+        assert_eq!(binary.len(), header + 30 * 3 * size);
+    }
+}
@@ -1,18 +1,28 @@
-use cgmath::{InnerSpace, Matrix3, Quaternion, SquareMatrix, Vector3, Zero};
+use cgmath::{
+    ElementWise, InnerSpace, Matrix, Matrix3, Quaternion, SquareMatrix, Vector3, VectorSpace, Zero,
+};
 use derive_setters::Setters;
+use serde::{Deserialize, Serialize};
 
-use crate::{frame::Frame, geometry::integrate::RigidMetrics};
+use crate::{
+    frame::Frame,
+    geometry::integrate::{MetricsError, RigidMetrics},
+};
 
-#[derive(Debug, Clone, Copy, Setters)]
+#[derive(Debug, Clone, Copy, Setters, Serialize, Deserialize)]
 pub struct Rigid {
     /// Mass in `kg`
     pub inverse_mass: f64,
 
     /// Inverse rotational inertia tensor.
-    /// Since the geometry is assumed to be in rest space,
-    /// this tensor is sparse and just the diagonal entries are stored.
+    /// Since the tensor is expressed in principal space,
+    /// it is sparse and just the diagonal entries are stored.
     /// Measured in `kg^-1 m^-2`.
-    pub inverse_inertia: Matrix3<f64>,
+    pub inverse_inertia: Vector3<f64>,
+
+    /// The rotation from principal space to rest space.
+    /// Principal space is the rest space rotated such that the inertia tensor becomes diagonal.
+    pub principal_rotation: Quaternion<f64>,
 
     /// Force acting on the rigid body outside its frame.
     /// Measured in `N`.
@@ -30,6 +40,44 @@ pub struct Rigid {
     /// Measrued in `N m`.
     pub internal_torque: Vector3<f64>,
 
+    /// Force acting on the rigid body during the next step only.
+    /// Measured in `N`.
+    pub accumulated_force: Vector3<f64>,
+
+    /// Torque acting on the rigid body during the next step only.
+    /// Measured in `N m`.
+    pub accumulated_torque: Vector3<f64>,
+
+    /// Linear impulse applied at the beginning of the next step.
+    /// Measured in `N s`.
+    pub accumulated_impulse: Vector3<f64>,
+
+    /// Angular impulse applied at the beginning of the next step.
+    /// Measured in `N m s`.
+    pub accumulated_angular_impulse: Vector3<f64>,
+
+    /// Factor by which the world's gravity accelerates this rigid body.
+    /// A scale of zero exempts the body from gravity.
+    pub gravity_scale: f64,
+
+    /// Ratio of separating to approaching normal speed in collisions.
+    /// The restitution of both colliding bodies is averaged.
+    pub restitution: f64,
+
+    /// Rate at which the velocity decays, e.g. due to air drag.
+    /// Measured in `s^-1`.
+    pub linear_damping: f64,
+
+    /// Rate at which the angular velocity decays.
+    /// Measured in `s^-1`.
+    pub angular_damping: f64,
+
+    /// Upper bound of the speed in `m s^-1`, enforced at the end of every substep.
+    pub max_linear_speed: Option<f64>,
+
+    /// Upper bound of the angular speed in `s^-1`, enforced at the end of every substep.
+    pub max_angular_speed: Option<f64>,
+
     /// Current velocity of the rigid body in `m s^-1`
     pub velocity: Vector3<f64>,
 
@@ -46,28 +94,87 @@ pub struct Rigid {
     /// One transforms from rest space to object space by translating the origin to the center of mass.
     pub rotation: Quaternion<f64>,
 
+    /// Index of the shape in the world's shape list.
+    pub shape: usize,
+
+    /// Kinetic energy per mass in `J kg^-1` below which the rigid body is considered at rest.
+    /// Falls back to the threshold of the solver configuration if absent.
+    pub sleep_threshold: Option<f64>,
+
+    /// How long the rigid body has been at rest, in `s`.
+    pub sleep_time: f64,
+
+    /// Sleeping rigid bodies are neither integrated nor solved until something wakes them up.
+    pub asleep: bool,
+
+    /// Index of the island the rigid body belonged to at the end of the last step.
+    /// Rigid bodies in the same island are connected by contacts or joints, and sleep and wake together.
+    pub island: usize,
+
     pub color: Option<[f32; 3]>,
 }
 
 impl Rigid {
-    pub fn new(metrics: RigidMetrics) -> Rigid {
-        Rigid {
+    /// A rigid body at rest at the origin.
+    /// Fails if the metrics are degenerate, see [`RigidMetrics::validate`].
+    pub fn new(metrics: RigidMetrics) -> Result<Rigid, MetricsError> {
+        metrics.validate()?;
+        let (moments, principal_rotation) = metrics.principal_axes();
+        let inverse_inertia = moments.map(f64::recip);
+
+        Ok(Rigid {
             inverse_mass: 1.0 / metrics.mass,
-            inverse_inertia: metrics
-                .inertia_tensor
-                .invert()
-                .expect("Inertia tensor is not invertible"),
+            inverse_inertia,
+            principal_rotation,
             internal_force: Vector3::zero(),
             external_force: Vector3::zero(),
             internal_torque: Vector3::zero(),
+            accumulated_force: Vector3::zero(),
+            accumulated_torque: Vector3::zero(),
+            accumulated_impulse: Vector3::zero(),
+            accumulated_angular_impulse: Vector3::zero(),
             external_torque: Vector3::zero(),
+            gravity_scale: 1.0,
+            restitution: 0.0,
             velocity: Vector3::zero(),
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            max_linear_speed: None,
+            max_angular_speed: None,
             angular_velocity: Vector3::zero(),
             center_of_mass: metrics.center_of_mass,
             position: Vector3::zero(),
             rotation: Quaternion::from_sv(1.0, Vector3::zero()),
+            shape: 0,
+            sleep_threshold: None,
+            sleep_time: 0.0,
+            asleep: false,
+            island: 0,
             color: None,
-        }
+        })
+    }
+
+    /// The inverse inertia tensor in world space.
+    /// Measured in `kg^-1 m^-2`.
+    pub fn inverse_inertia_tensor(&self) -> Matrix3<f64> {
+        self.principal_tensor(self.inverse_inertia)
+    }
+
+    /// The inertia tensor in world space.
+    /// Measured in `kg m^2`.
+    pub fn inertia_tensor(&self) -> Matrix3<f64> {
+        self.principal_tensor(self.inverse_inertia.map(f64::recip))
+    }
+
+    /// Transforms a tensor which is diagonal in principal space into world space.
+    fn principal_tensor(&self, diagonal: Vector3<f64>) -> Matrix3<f64> {
+        let rotation = Matrix3::from(self.rotation * self.principal_rotation);
+        let diagonal = Matrix3::from_cols(
+            diagonal.x * Vector3::unit_x(),
+            diagonal.y * Vector3::unit_y(),
+            diagonal.z * Vector3::unit_z(),
+        );
+        rotation * diagonal * rotation.transpose()
     }
 
     /// A frame which transforms from object space to world space.
@@ -79,13 +186,130 @@ impl Rigid {
         }
     }
 
-    pub fn integrate(&mut self, dt: f64) {
-        let force = self.external_force + self.rotation * self.internal_force;
-        self.velocity += dt * force * self.inverse_mass;
+    /// The center of mass in world space.
+    pub fn world_center_of_mass(&self) -> Vector3<f64> {
+        self.position + self.center_of_mass
+    }
+
+    /// The frame between this state and a later one of the same rigid body, for rendering in between steps.
+    /// Since the body rotates about its center of mass, the center of mass moves in a straight line,
+    /// while object space origins off the center of mass move along an arc.
+    pub fn interpolated_frame(&self, next: &Rigid, amount: f64) -> Frame {
+        let center = self
+            .world_center_of_mass()
+            .lerp(next.world_center_of_mass(), amount);
+        let rotation = self.rotation.slerp(next.rotation, amount);
+        Frame {
+            position: center - rotation * next.center_of_mass,
+            rotation,
+        }
+    }
+
+    /// Translational and rotational kinetic energy in `J`.
+    pub fn kinetic_energy(&self) -> f64 {
+        0.5 * self.velocity.magnitude2() / self.inverse_mass
+            + 0.5
+                * self
+                    .angular_velocity
+                    .dot(self.inertia_tensor() * self.angular_velocity)
+    }
+
+    /// Linear momentum in `kg m s^-1`.
+    pub fn momentum(&self) -> Vector3<f64> {
+        self.velocity / self.inverse_mass
+    }
+
+    /// The linear impulse gravity, forces and accumulated impulses will exert during the next step of `dt`
+    /// in `N s`. Static and sleeping rigid bodies are not moved by them.
+    pub fn external_impulse(&self, dt: f64, gravity: Vector3<f64>) -> Vector3<f64> {
+        if self.asleep || self.inverse_mass == 0.0 {
+            return Vector3::zero();
+        }
+        let force =
+            self.external_force + self.rotation * self.internal_force + self.accumulated_force;
+        dt * (self.gravity_scale * gravity / self.inverse_mass + force) + self.accumulated_impulse
+    }
+
+    /// Angular momentum about a world space point in `kg m^2 s^-1`,
+    /// made up of the spin about the center of mass and the orbit of the center of mass around the point.
+    pub fn angular_momentum(&self, point: Vector3<f64>) -> Vector3<f64> {
+        (self.world_center_of_mass() - point).cross(self.momentum())
+            + self.inertia_tensor() * self.angular_velocity
+    }
+
+    /// Gravitational potential energy in `J`, relative to the ground plane.
+    pub fn potential_energy(&self, gravity: Vector3<f64>) -> f64 {
+        -self.gravity_scale * gravity.dot(self.world_center_of_mass()) / self.inverse_mass
+    }
+
+    /// Wakes the rigid body up and restarts its rest timer.
+    /// This only affects the body itself, see `World::wake` to wake its whole island.
+    pub fn wake(&mut self) {
+        self.asleep = false;
+        self.sleep_time = 0.0;
+    }
+
+    /// Adds a force acting at a world space point during the next step.
+    /// Off-center points additionally exert a torque.
+    pub fn add_force_at_point(&mut self, force: Vector3<f64>, point: Vector3<f64>) {
+        self.wake();
+        self.accumulated_force += force;
+        self.accumulated_torque += (point - self.world_center_of_mass()).cross(force);
+    }
+
+    /// Adds a world space force acting at an object space point during the next step.
+    pub fn add_force_at_local_point(&mut self, force: Vector3<f64>, point: Vector3<f64>) {
+        self.add_force_at_point(force, self.frame() * point);
+    }
+
+    /// Adds a torque acting during the next step.
+    pub fn add_torque(&mut self, torque: Vector3<f64>) {
+        self.wake();
+        self.accumulated_torque += torque;
+    }
+
+    /// Adds an impulse at a world space point, which changes the velocities at the beginning of the next step.
+    pub fn add_impulse_at_point(&mut self, impulse: Vector3<f64>, point: Vector3<f64>) {
+        self.wake();
+        self.accumulated_impulse += impulse;
+        self.accumulated_angular_impulse += (point - self.world_center_of_mass()).cross(impulse);
+    }
+
+    /// Adds a world space impulse at an object space point.
+    pub fn add_impulse_at_local_point(&mut self, impulse: Vector3<f64>, point: Vector3<f64>) {
+        self.add_impulse_at_point(impulse, self.frame() * point);
+    }
+
+    /// Converts the accumulated impulses into velocity changes.
+    pub fn apply_accumulated_impulses(&mut self) {
+        self.velocity += self.inverse_mass * self.accumulated_impulse;
+        self.angular_velocity += self.inverse_inertia_tensor() * self.accumulated_angular_impulse;
+        self.accumulated_impulse = Vector3::zero();
+        self.accumulated_angular_impulse = Vector3::zero();
+    }
+
+    /// Resets all forces, torques and impulses which were only meant to act during a single step.
+    pub fn clear_accumulators(&mut self) {
+        self.accumulated_force = Vector3::zero();
+        self.accumulated_torque = Vector3::zero();
+        self.accumulated_impulse = Vector3::zero();
+        self.accumulated_angular_impulse = Vector3::zero();
+    }
+
+    /// Advances the rigid body by explicit integration.
+    /// Gravity is given as an acceleration in `m s^-2`, so all bodies fall alike regardless of their mass.
+    pub fn integrate(&mut self, dt: f64, gravity: Vector3<f64>) {
+        let force =
+            self.external_force + self.rotation * self.internal_force + self.accumulated_force;
+        self.velocity += dt * (self.gravity_scale * gravity + force * self.inverse_mass);
+        self.velocity = damp(self.velocity, self.linear_damping, dt);
         self.position += dt * self.velocity;
 
-        let torque = self.external_torque + self.rotation * self.internal_torque;
-        self.angular_velocity += dt * self.inverse_inertia * torque;
+        let torque =
+            self.external_torque + self.rotation * self.internal_torque + self.accumulated_torque;
+        self.angular_velocity += dt * self.inverse_inertia_tensor() * torque;
+        self.angular_velocity = self.gyroscopic_angular_velocity(dt);
+        self.angular_velocity = damp(self.angular_velocity, self.angular_damping, dt);
         let delta_rotation = dt
             * 0.5
             * Quaternion::new(
@@ -98,6 +322,29 @@ impl Rigid {
         self.rotation = (self.rotation + delta_rotation).normalize();
     }
 
+    /// Accounts for the gyroscopic torque `-ω × (I ω)` by a single Newton step of implicit Euler,
+    /// which, unlike the explicit update, does not gain energy for fast spinning bodies.
+    /// The step is carried out in principal space, where the inertia tensor is diagonal.
+    fn gyroscopic_angular_velocity(&self, dt: f64) -> Vector3<f64> {
+        let rotation = self.rotation * self.principal_rotation;
+        let inertia = self.inverse_inertia.map(f64::recip);
+        let diagonal = Matrix3::from_cols(
+            inertia.x * Vector3::unit_x(),
+            inertia.y * Vector3::unit_y(),
+            inertia.z * Vector3::unit_z(),
+        );
+
+        let omega = rotation.conjugate() * self.angular_velocity;
+        let momentum = omega.mul_element_wise(inertia);
+        let residual = dt * omega.cross(momentum);
+        let jacobian = diagonal + dt * (skew(omega) * diagonal - skew(momentum));
+
+        match jacobian.invert() {
+            Some(inverse) => rotation * (omega - inverse * residual),
+            None => self.angular_velocity,
+        }
+    }
+
     pub fn derive(&mut self, position: Vector3<f64>, rotation: Quaternion<f64>, dt: f64) {
         self.velocity = (self.position - position) / dt;
 
@@ -108,17 +355,261 @@ impl Rigid {
         self.angular_velocity = 2.0 * delta.v / dt;
     }
 
-    /// Applies a linear impulse in a given direction and magnitude at a given
+    /// Clamps the velocities to the maximal speeds.
+    /// This happens after the velocities were derived from the solved positions,
+    /// so that deep penetrations cannot launch the body faster.
+    pub fn limit_speeds(&mut self) {
+        self.velocity = limit(self.velocity, self.max_linear_speed);
+        self.angular_velocity = limit(self.angular_velocity, self.max_angular_speed);
+    }
+
+    /// Changes the velocities right away by a linear impulse at a world space point.
+    pub fn apply_velocity_impulse(&mut self, impulse: Vector3<f64>, point: Vector3<f64>) {
+        let arm = point - self.world_center_of_mass();
+        self.velocity += impulse * self.inverse_mass;
+        self.angular_velocity += self.inverse_inertia_tensor() * arm.cross(impulse);
+    }
+
+    /// Applies a linear impulse in a given direction and magnitude at a given world space point.
     /// Results in changes in both position and rotation.
     pub fn apply_impulse(&mut self, impulse: Vector3<f64>, point: Vector3<f64>) {
-        self.position += impulse * self.inverse_mass;
+        let arm = point - self.world_center_of_mass();
+        let rotation = self.inverse_inertia_tensor() * arm.cross(impulse);
 
-        self.rotation +=
-            0.5 * Quaternion::from_sv(
-                0.0,
-                (self.inverse_inertia * (point - (self.position + self.center_of_mass)))
-                    .cross(impulse),
-            ) * self.rotation;
+        self.position += impulse * self.inverse_mass;
+        self.rotation += 0.5 * Quaternion::from_sv(0.0, rotation) * self.rotation;
         self.rotation = self.rotation.normalize();
     }
 }
+
+/// Decays a velocity at the given rate.
+/// The decay is integrated implicitly, so it stays stable for any rate.
+fn damp(velocity: Vector3<f64>, damping: f64, dt: f64) -> Vector3<f64> {
+    velocity / (1.0 + dt * damping)
+}
+
+/// Clamps the magnitude of a velocity.
+fn limit(velocity: Vector3<f64>, max_speed: Option<f64>) -> Vector3<f64> {
+    match max_speed {
+        Some(max_speed) if velocity.magnitude() > max_speed => max_speed * velocity.normalize(),
+        _ => velocity,
+    }
+}
+
+/// The matrix form of the cross product `v × _`.
+fn skew(v: Vector3<f64>) -> Matrix3<f64> {
+    Matrix3::new(0.0, v.z, -v.y, -v.z, 0.0, v.x, v.y, -v.x, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, Euler, InnerSpace, Quaternion, Rotation3, Vector3, Zero};
+
+    use super::Rigid;
+    use crate::{
+        constraint::Constraint, debug::DebugLines, frame::Frame, geometry::Polytope, world::World,
+    };
+
+    /// An asymmetric box whose principal axes are neither aligned with object space nor world space.
+    fn tumbler() -> Rigid {
+        let polytope = Polytope::new_box(vec3(1.0, 0.5, 0.25)).transformed(Frame {
+            position: vec3(0.3, -0.2, 0.1),
+            rotation: Euler::new(Deg(20.0), Deg(-35.0), Deg(50.0)).into(),
+        });
+        Rigid::new(polytope.rigid_metrics(1.0))
+            .unwrap()
+            .rotation(Euler::new(Deg(-60.0), Deg(10.0), Deg(25.0)).into())
+    }
+
+    fn angular_momentum(rigid: &Rigid) -> Vector3<f64> {
+        rigid.inertia_tensor() * rigid.angular_velocity
+    }
+
+    #[test]
+    fn interpolated_frames_rotate_about_the_center_of_mass() {
+        let previous = tumbler();
+        let next = previous
+            .position(previous.position + vec3(1.0, 0.0, 0.0))
+            .rotation(Quaternion::from_angle_z(Deg(90.0)) * previous.rotation);
+        let halfway = previous.interpolated_frame(&next, 0.5);
+
+        let center = halfway * previous.center_of_mass;
+        let expected = 0.5 * (previous.world_center_of_mass() + next.world_center_of_mass());
+        assert!((center - expected).magnitude() < 1e-12);
+
+        // A point off the center of mass keeps its distance to it, instead of cutting the arc short.
+        let point = vec3(2.0, 1.0, -1.0);
+        let radius = (point - previous.center_of_mass).magnitude();
+        assert!(((halfway * point - center).magnitude() - radius).abs() < 1e-12);
+
+        let end = previous.interpolated_frame(&next, 1.0);
+        assert!((end.position - next.frame().position).magnitude() < 1e-12);
+    }
+
+    #[test]
+    fn torque_free_spin_conserves_angular_momentum() {
+        for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+            let mut rigid = tumbler();
+            rigid.angular_velocity = 3.0 * (rigid.rotation * rigid.principal_rotation * axis);
+            let initial = angular_momentum(&rigid);
+
+            for _ in 0..1000 {
+                rigid.integrate(1e-3, Vector3::zero());
+            }
+
+            assert!((angular_momentum(&rigid) - initial).magnitude() < 1e-9 * initial.magnitude());
+        }
+    }
+
+    #[test]
+    fn torque_free_tumbling_conserves_angular_momentum() {
+        let mut rigid = tumbler();
+        rigid.angular_velocity = vec3(2.0, -3.0, 1.5);
+        let initial = angular_momentum(&rigid);
+
+        for _ in 0..1000 {
+            rigid.integrate(1e-3, Vector3::zero());
+        }
+
+        assert!((angular_momentum(&rigid) - initial).magnitude() < 1e-2 * initial.magnitude());
+    }
+
+    #[test]
+    fn torque_changes_angular_momentum_in_world_space() {
+        let mut rigid = tumbler();
+        rigid.external_torque = vec3(0.2, -0.7, 0.4);
+        let inertia = rigid.inertia_tensor();
+        let dt = 1e-3;
+
+        rigid.integrate(dt, Vector3::zero());
+
+        let expected = dt * rigid.external_torque;
+        let actual = inertia * rigid.angular_velocity;
+        assert!((actual - expected).magnitude() < 1e-4 * expected.magnitude());
+    }
+
+    #[test]
+    fn impulse_response_matches_constraint_resistance() {
+        let mut rigid = tumbler();
+        let point = rigid.frame() * vec3(1.0, 0.5, 0.25);
+        let direction = vec3(0.3, -1.0, 0.5).normalize();
+        let constraint = Constraint::attach(0, rigid.frame().inverse() * point, point, 0.0);
+        let resistance = constraint.inverse_resitance(&[rigid], direction);
+
+        let local = rigid.frame().inverse() * point;
+        let magnitude = 1e-6;
+        rigid.apply_impulse(magnitude * direction, point);
+        let displacement = (rigid.frame() * local - point).dot(direction);
+
+        assert!((displacement - magnitude * resistance).abs() < 1e-3 * magnitude * resistance);
+    }
+
+    #[test]
+    fn damping_decays_velocities_every_substep() {
+        let mut rigid = Rigid::new(Polytope::new_cube().rigid_metrics(1.0))
+            .unwrap()
+            .linear_damping(2.0)
+            .angular_damping(3.0)
+            .velocity(vec3(1.0, -2.0, 0.5))
+            .angular_velocity(vec3(0.0, 0.0, 4.0));
+        let (velocity, angular_velocity) = (rigid.velocity, rigid.angular_velocity);
+
+        let dt = 1e-3;
+        for _ in 0..1000 {
+            rigid.integrate(dt, Vector3::zero());
+        }
+
+        // Every substep divides by `1 + dt c`, which approaches `exp(-c t)` for small substeps.
+        let linear = (1.0 + dt * 2.0_f64).powi(-1000);
+        let angular = (1.0 + dt * 3.0_f64).powi(-1000);
+        assert!((rigid.velocity - linear * velocity).magnitude() < 1e-12);
+        assert!((rigid.angular_velocity - angular * angular_velocity).magnitude() < 1e-12);
+        assert!((linear / (-2.0_f64).exp() - 1.0).abs() < 1e-2);
+        assert!((angular / (-3.0_f64).exp() - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn speed_limits_hold_after_resolving_deep_penetrations() {
+        let sunken = |limit: Option<f64>| {
+            let mut world = World::default();
+            let cube = world.add_shape(Polytope::new_cube().into());
+            let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+                .unwrap()
+                .shape(cube)
+                .max_linear_speed(limit)
+                .max_angular_speed(limit);
+            rigid.position = vec3(0.0, 0.0, -0.6);
+            rigid.rotation = Euler::new(Deg(10.0), Deg(5.0), Deg(0.0)).into();
+            world.add_rigid(rigid);
+            world.integrate(1.0 / 60.0, &mut DebugLines::default());
+            world.rigids[0]
+        };
+
+        let free = sunken(None);
+        assert!(free.velocity.magnitude() > 2.0);
+
+        let limited = sunken(Some(2.0));
+        assert!(limited.velocity.magnitude() <= 2.0 + 1e-12);
+        assert!(limited.angular_velocity.magnitude() <= 2.0 + 1e-12);
+    }
+
+    #[test]
+    fn off_center_forces_exert_torque() {
+        let mut rigid = Rigid::new(Polytope::new_cube().rigid_metrics(1.0)).unwrap();
+        let force = vec3(0.0, 2.0, 0.0);
+        rigid.add_force_at_point(force, rigid.world_center_of_mass() + vec3(0.5, 0.0, 0.0));
+        rigid.add_force_at_local_point(force, vec3(0.5, 0.5, 0.5));
+        rigid.add_torque(vec3(0.0, 0.0, -0.25));
+
+        assert_eq!(rigid.accumulated_force, 2.0 * force);
+        assert!((rigid.accumulated_torque - vec3(0.0, 0.0, 0.75)).magnitude() < 1e-12);
+    }
+
+    /// A cube floating far above the ground in a world without gravity.
+    fn floating() -> World {
+        let mut world = World {
+            gravity: Vector3::zero(),
+            ..Default::default()
+        };
+        let cube = world.add_shape(Polytope::new_cube().into());
+        let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(2.0))
+            .unwrap()
+            .shape(cube);
+        rigid.position = vec3(0.0, 0.0, 10.0);
+        world.add_rigid(rigid);
+        world
+    }
+
+    #[test]
+    fn impulses_change_the_velocity_in_the_next_step() {
+        let mut world = floating();
+        let impulse = vec3(1.0, -0.5, 0.25);
+        let center = world.rigids[0].world_center_of_mass();
+        world.rigids[0].add_impulse_at_point(impulse, center);
+
+        world.integrate(1.0 / 60.0, &mut DebugLines::default());
+
+        let rigid = &world.rigids[0];
+        assert!((rigid.velocity - rigid.inverse_mass * impulse).magnitude() < 1e-9);
+        assert!(rigid.angular_velocity.magnitude() < 1e-9);
+    }
+
+    #[test]
+    fn accumulators_are_cleared_after_a_step() {
+        let mut world = floating();
+        let external_force = vec3(0.0, 0.0, 1.0);
+        world.rigids[0].external_force = external_force;
+        world.rigids[0].add_force_at_local_point(vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        world.rigids[0].add_torque(vec3(0.0, 1.0, 0.0));
+        world.rigids[0].add_impulse_at_local_point(vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0));
+
+        world.integrate(1.0 / 60.0, &mut DebugLines::default());
+
+        let rigid = &world.rigids[0];
+        assert_eq!(rigid.accumulated_force, Vector3::zero());
+        assert_eq!(rigid.accumulated_torque, Vector3::zero());
+        assert_eq!(rigid.accumulated_impulse, Vector3::zero());
+        assert_eq!(rigid.accumulated_angular_impulse, Vector3::zero());
+        assert_eq!(rigid.external_force, external_force);
+    }
+}
@@ -0,0 +1,742 @@
+//! Scenes describe the initial state of a world in RON or JSON, so that they can be authored without recompiling.
+//! Shapes and materials are referred to by name, bodies by their index.
+//! See `scenes/demo.ron` for an example.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use cgmath::{vec2, vec3, Deg, Euler, Quaternion, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constraint::Constraint,
+    frame::Frame,
+    geometry::{
+        compound::Compound,
+        decomposition::{DecompositionParameters, TriangleMesh},
+        heightfield::Heightfield,
+        integrate::MetricsError,
+        Polytope,
+    },
+    rigid::Rigid,
+    solver::SolverConfig,
+    world::World,
+};
+
+/// Names of the built-in scenes, as accepted by `by_name`.
+pub const NAMES: [&str; 6] = ["demo", "stack", "pile", "pyramid", "chain", "terrain"];
+
+/// Builds the built-in scene of the given name.
+pub fn by_name(name: &str) -> Option<World> {
+    match name {
+        "demo" => Some(World::new()),
+        "stack" => Some(stack(5)),
+        "pile" => Some(pile(24)),
+        "pyramid" => Some(pyramid(10)),
+        "chain" => Some(chain(20)),
+        "terrain" => Some(terrain(64)),
+        _ => None,
+    }
+}
+
+/// A tower of unit cubes resting on top of each other.
+pub fn stack(height: usize) -> World {
+    let mut world = World::default();
+    let cube = world.add_shape(Polytope::new_cube().into());
+    for i in 0..height {
+        let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+            .unwrap()
+            .shape(cube);
+        rigid.position.z = 1.01 * i as f64;
+        world.add_rigid(rigid);
+    }
+    world
+}
+
+/// Tumbling cubes dropped in layers onto the ground.
+pub fn pile(count: usize) -> World {
+    let mut world = World::default();
+    let cube = world.add_shape(Polytope::new_cube().into());
+    for i in 0..count {
+        let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+            .unwrap()
+            .shape(cube);
+        rigid.position = vec3(
+            (i % 4) as f64 * 1.1,
+            (i / 4 % 2) as f64 * 2.5,
+            1.5 * (i / 8) as f64,
+        );
+        rigid.angular_velocity = vec3(0.1 * i as f64, -0.2, 0.3);
+        world.add_rigid(rigid);
+    }
+    world
+}
+
+/// A wall of unit cubes, each row one cube shorter than the one below.
+pub fn pyramid(base: usize) -> World {
+    let mut world = World::default();
+    let cube = world.add_shape(Polytope::new_cube().into());
+    for row in 0..base {
+        for i in 0..base - row {
+            let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+                .unwrap()
+                .shape(cube);
+            rigid.position = vec3(1.05 * (i as f64 + 0.5 * row as f64), 0.0, 1.01 * row as f64);
+            world.add_rigid(rigid);
+        }
+    }
+    world
+}
+
+/// Bars hanging from a fixed point, linked end to end by joints.
+/// The chain starts out horizontal, so that it swings down and folds onto the ground.
+pub fn chain(links: usize) -> World {
+    let mut world = World::default();
+    let bar = world.add_shape(Polytope::new_box(vec3(0.2, 0.05, 0.05)).into());
+    let end = vec3(0.25, 0.0, 0.0);
+    for i in 0..links {
+        let mut rigid = Rigid::new(world.shapes[bar].rigid_metrics(1.0))
+            .unwrap()
+            .shape(bar);
+        rigid.position = vec3(0.5 * i as f64 + 0.25, 0.0, 0.5 * links as f64);
+        let index = world.add_rigid(rigid);
+        let joint = match index {
+            0 => Constraint::attach(0, -end, vec3(0.0, 0.0, 0.5 * links as f64), 0.0),
+            _ => Constraint::connect((index - 1, index), (end, -end), 0.0),
+        };
+        world.add_joint(joint.compliance(0.0));
+    }
+    world
+}
+
+/// Tumbling cubes dropped side by side onto gentle hills, which extend far enough for none of them to slide off.
+/// Further layers start above the first one once a row of eight by eight cubes is full.
+pub fn terrain(count: usize) -> World {
+    let mut world = World {
+        terrain: Some(Arc::new(Heightfield::from_fn(
+            vec2(-12.0, -12.0),
+            0.5,
+            (49, 49),
+            |x, y| 0.5 + 0.3 * (0.9 * x).sin() * (0.7 * y).cos(),
+        ))),
+        ..Default::default()
+    };
+    let cube = world.add_shape(Polytope::new_cube().into());
+    for i in 0..count {
+        let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+            .unwrap()
+            .shape(cube);
+        rigid.position = vec3(
+            (i % 8) as f64 * 1.6 - 5.6,
+            (i / 8 % 8) as f64 * 1.6 - 5.6,
+            1.5 + 2.0 * (i / 64) as f64,
+        );
+        rigid.angular_velocity = vec3(0.1 * (i % 7) as f64, -0.2, 0.3);
+        world.add_rigid(rigid);
+    }
+    world
+}
+
+/// Every subdivision of a geodesic sphere quadruples its faces, so that six of them already yield 81920.
+pub const MAX_SUBDIVISIONS: usize = 6;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    UnknownFormat(PathBuf),
+    Parse(String),
+    UnknownShape(String),
+    UnknownMaterial(String),
+    UnknownBody(usize),
+    InvalidShape(String, String),
+    InvalidBody(usize, MetricsError),
+    InvalidTerrain(String),
+    InvalidConfig(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "Cannot read {}: {error}", path.display()),
+            SceneError::UnknownFormat(path) => write!(
+                f,
+                "Cannot tell the format of {}, expected a .ron or .json file",
+                path.display()
+            ),
+            SceneError::Parse(error) => write!(f, "Cannot parse scene: {error}"),
+            SceneError::UnknownShape(name) => write!(f, "Unknown shape: {name}"),
+            SceneError::UnknownMaterial(name) => write!(f, "Unknown material: {name}"),
+            SceneError::UnknownBody(index) => write!(f, "Joint refers to missing body {index}"),
+            SceneError::InvalidShape(name, reason) => write!(f, "Invalid shape {name}: {reason}"),
+            SceneError::InvalidBody(index, error) => write!(f, "Invalid body {index}: {error}"),
+            SceneError::InvalidTerrain(reason) => write!(f, "Invalid terrain: {reason}"),
+            SceneError::InvalidConfig(reason) => write!(f, "Invalid solver config: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ron,
+    Json,
+}
+
+impl Format {
+    /// Tells the format by the file extension.
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "ron" => Some(Format::Ron),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    /// Measured in `m s^-2`.
+    pub gravity: Vector3<f64>,
+
+    pub config: SolverConfig,
+
+    pub materials: BTreeMap<String, Material>,
+
+    pub shapes: BTreeMap<String, Shape>,
+
+    pub bodies: Vec<Body>,
+
+    pub joints: Vec<Joint>,
+
+    pub terrain: Option<Heightfield>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        let world = World::default();
+        Scene {
+            gravity: world.gravity,
+            config: world.config,
+            materials: BTreeMap::new(),
+            shapes: BTreeMap::new(),
+            bodies: Vec::new(),
+            joints: Vec::new(),
+            terrain: None,
+        }
+    }
+}
+
+/// Physical properties shared by bodies.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    /// Measured in `kg m^-3`.
+    pub density: f64,
+
+    pub restitution: f64,
+
+    pub linear_damping: f64,
+
+    pub angular_damping: f64,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            density: 1.0,
+            restitution: 0.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Shape {
+    Tetrahedron,
+    Cube,
+    Octahedron,
+    Dodecahedron,
+    Icosahedron,
+    Box {
+        half_extents: Vector3<f64>,
+    },
+    Prism {
+        sides: usize,
+        radius: f64,
+        half_height: f64,
+    },
+    Cylinder {
+        segments: usize,
+        radius: f64,
+        half_height: f64,
+    },
+    /// Centered at its center of mass, a quarter of the height above the base.
+    Pyramid {
+        sides: usize,
+        radius: f64,
+        half_height: f64,
+    },
+    /// Centered at its center of mass, a quarter of the height above the base.
+    Cone {
+        segments: usize,
+        radius: f64,
+        half_height: f64,
+    },
+    /// At most `MAX_SUBDIVISIONS` subdivisions, since every one quadruples the faces.
+    GeodesicSphere {
+        subdivisions: usize,
+    },
+
+    /// The convex hull of a point cloud.
+    Hull(Vec<Vector3<f64>>),
+
+    /// A convex polytope given by its vertices and counter-clockwise faces.
+    Polytope {
+        vertices: Vec<Vector3<f64>>,
+        faces: Vec<Vec<usize>>,
+    },
+
+    /// A possibly concave mesh read from an OBJ file and decomposed into convex parts.
+    /// The path is relative to the scene file.
+    Mesh {
+        path: PathBuf,
+        #[serde(default)]
+        parameters: DecompositionParameters,
+    },
+
+    Compound(Vec<Part>),
+
+    /// Scales a shape uniformly about its origin.
+    Scaled {
+        factor: f64,
+        shape: Box<Shape>,
+    },
+}
+
+/// A shape placed within a compound.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Part {
+    #[serde(default = "Vector3::zero")]
+    pub position: Vector3<f64>,
+
+    /// Euler angles in degrees.
+    #[serde(default = "Vector3::zero")]
+    pub rotation: Vector3<f64>,
+
+    pub shape: Shape,
+}
+
+/// The initial state of a rigid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Body {
+    pub shape: String,
+
+    /// Bodies without a material use the default one.
+    pub material: Option<String>,
+
+    pub position: Vector3<f64>,
+
+    /// Euler angles in degrees.
+    pub rotation: Vector3<f64>,
+
+    pub velocity: Vector3<f64>,
+
+    pub angular_velocity: Vector3<f64>,
+
+    /// Constant force in `N` acting on the center of mass.
+    pub force: Vector3<f64>,
+
+    /// Constant torque in `N m`.
+    pub torque: Vector3<f64>,
+
+    pub gravity_scale: f64,
+
+    pub color: Option<[f32; 3]>,
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Body {
+            shape: String::new(),
+            material: None,
+            position: Vector3::zero(),
+            rotation: Vector3::zero(),
+            velocity: Vector3::zero(),
+            angular_velocity: Vector3::zero(),
+            force: Vector3::zero(),
+            torque: Vector3::zero(),
+            gravity_scale: 1.0,
+            color: None,
+        }
+    }
+}
+
+/// A distance constraint between points on two bodies, or between a body and a point in world space.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Joint {
+    pub bodies: (usize, Option<usize>),
+
+    /// Attachment points in the object space of the respective body,
+    /// or in world space if there is no second body.
+    pub anchors: (Vector3<f64>, Vector3<f64>),
+
+    #[serde(default)]
+    pub distance: f64,
+
+    /// Inverse stiffness in `m N^-1`.
+    #[serde(default)]
+    pub compliance: f64,
+}
+
+impl Scene {
+    pub fn parse(source: &str, format: Format) -> Result<Scene, SceneError> {
+        match format {
+            Format::Ron => {
+                ron::from_str(source).map_err(|error| SceneError::Parse(error.to_string()))
+            }
+            Format::Json => {
+                serde_json::from_str(source).map_err(|error| SceneError::Parse(error.to_string()))
+            }
+        }
+    }
+
+    pub fn to_string(&self, format: Format) -> String {
+        match format {
+            Format::Ron => ron::ser::to_string_pretty(self, Default::default()).unwrap(),
+            Format::Json => serde_json::to_string_pretty(self).unwrap(),
+        }
+    }
+
+    /// Builds the world described by the scene.
+    /// Mesh paths are resolved relative to `directory`.
+    pub fn build(&self, directory: &Path) -> Result<World, SceneError> {
+        self.config.validate().map_err(SceneError::InvalidConfig)?;
+        if let Some(terrain) = &self.terrain {
+            terrain.validate().map_err(SceneError::InvalidTerrain)?;
+        }
+
+        let mut world = World {
+            gravity: self.gravity,
+            config: self.config,
+            terrain: self.terrain.clone().map(Arc::new),
+            ..Default::default()
+        };
+
+        let mut shapes = BTreeMap::new();
+        for (name, shape) in &self.shapes {
+            let compound = shape
+                .compound(directory)
+                .map_err(|reason| SceneError::InvalidShape(name.clone(), reason))?;
+            shapes.insert(name, world.add_shape(compound));
+        }
+
+        for (index, body) in self.bodies.iter().enumerate() {
+            let shape = *shapes
+                .get(&body.shape)
+                .ok_or_else(|| SceneError::UnknownShape(body.shape.clone()))?;
+            let material = match &body.material {
+                Some(name) => *self
+                    .materials
+                    .get(name)
+                    .ok_or_else(|| SceneError::UnknownMaterial(name.clone()))?,
+                None => Material::default(),
+            };
+
+            let metrics = world.shapes[shape].rigid_metrics(material.density);
+            let mut rigid = Rigid::new(metrics)
+                .map_err(|error| SceneError::InvalidBody(index, error))?
+                .shape(shape)
+                .restitution(material.restitution)
+                .linear_damping(material.linear_damping)
+                .angular_damping(material.angular_damping)
+                .gravity_scale(body.gravity_scale)
+                .color(body.color);
+            rigid.position = body.position;
+            rigid.rotation = euler(body.rotation);
+            rigid.velocity = body.velocity;
+            rigid.angular_velocity = body.angular_velocity;
+            rigid.external_force = body.force;
+            rigid.external_torque = body.torque;
+            world.add_rigid(rigid);
+        }
+
+        for joint in &self.joints {
+            let body = |index: usize| {
+                if index < self.bodies.len() {
+                    Ok(index)
+                } else {
+                    Err(SceneError::UnknownBody(index))
+                }
+            };
+            let first = body(joint.bodies.0)?;
+            let constraint = match joint.bodies.1 {
+                Some(second) => {
+                    Constraint::connect((first, body(second)?), joint.anchors, joint.distance)
+                }
+                None => Constraint::attach(first, joint.anchors.0, joint.anchors.1, joint.distance),
+            };
+            world.add_joint(constraint.compliance(joint.compliance));
+        }
+
+        Ok(world)
+    }
+}
+
+impl Shape {
+    fn compound(&self, directory: &Path) -> Result<Compound, String> {
+        if let Shape::Prism { sides, .. }
+        | Shape::Pyramid { sides, .. }
+        | Shape::Cylinder {
+            segments: sides, ..
+        }
+        | Shape::Cone {
+            segments: sides, ..
+        } = self
+        {
+            if *sides < 3 {
+                return Err(format!("Needs at least three sides, got {sides}"));
+            }
+        }
+
+        if let &Shape::GeodesicSphere { subdivisions } = self {
+            if subdivisions > MAX_SUBDIVISIONS {
+                return Err(format!(
+                    "At most {MAX_SUBDIVISIONS} subdivisions are supported, got {subdivisions}"
+                ));
+            }
+        }
+
+        let polytope = match self {
+            Shape::Tetrahedron => Polytope::new_tetrahedron(),
+            Shape::Cube => Polytope::new_cube(),
+            Shape::Octahedron => Polytope::new_octahedron(),
+            Shape::Dodecahedron => Polytope::new_dodecahedron(),
+            Shape::Icosahedron => Polytope::new_icosahedron(),
+            &Shape::Box { half_extents } => Polytope::new_box(half_extents),
+            &Shape::Prism {
+                sides,
+                radius,
+                half_height,
+            } => Polytope::new_prism(sides, radius, half_height),
+            &Shape::Cylinder {
+                segments,
+                radius,
+                half_height,
+            } => Polytope::new_cylinder(segments, radius, half_height),
+            &Shape::Pyramid {
+                sides,
+                radius,
+                half_height,
+            } => Polytope::new_pyramid(sides, radius, half_height),
+            &Shape::Cone {
+                segments,
+                radius,
+                half_height,
+            } => Polytope::new_cone(segments, radius, half_height),
+            &Shape::GeodesicSphere { subdivisions } => Polytope::new_geodesic_sphere(subdivisions),
+            Shape::Hull(points) => Polytope::convex_hull(points)
+                .ok_or_else(|| "The points do not span a volume".to_string())?,
+            Shape::Polytope { vertices, faces } => {
+                let polytope = Polytope::new(vertices.clone(), faces.clone());
+                polytope.validate()?;
+                polytope
+            }
+            Shape::Mesh { path, parameters } => {
+                let path = directory.join(path);
+                let source = fs::read_to_string(&path)
+                    .map_err(|error| format!("Cannot read {}: {error}", path.display()))?;
+                let mesh = TriangleMesh::from_obj(&source)?;
+                return Compound::decompose(&mesh, *parameters)
+                    .ok_or_else(|| format!("{} encloses no volume", path.display()));
+            }
+            Shape::Compound(parts) => {
+                let mut compound = Compound::new(Vec::new());
+                for part in parts {
+                    let frame = Frame {
+                        position: part.position,
+                        rotation: euler(part.rotation),
+                    };
+                    compound.parts.extend(
+                        part.shape
+                            .compound(directory)?
+                            .parts
+                            .into_iter()
+                            .map(|(inner, polytope)| (frame * inner, polytope)),
+                    );
+                }
+                return Ok(compound);
+            }
+            Shape::Scaled { factor, shape } => {
+                let mut compound = shape.compound(directory)?;
+                for (frame, polytope) in &mut compound.parts {
+                    frame.position *= *factor;
+                    *polytope = *factor * polytope.clone();
+                }
+                return Ok(compound);
+            }
+        };
+        Ok(polytope.into())
+    }
+}
+
+/// Loads a scene file, telling its format by the extension, and builds its world.
+pub fn load(path: &Path) -> Result<World, SceneError> {
+    let format = Format::from_path(path).ok_or_else(|| SceneError::UnknownFormat(path.into()))?;
+    let source = fs::read_to_string(path).map_err(|error| SceneError::Io(path.into(), error))?;
+    Scene::parse(&source, format)?.build(path.parent().unwrap_or(Path::new(".")))
+}
+
+fn euler(degrees: Vector3<f64>) -> Quaternion<f64> {
+    Euler::new(Deg(degrees.x), Deg(degrees.y), Deg(degrees.z)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use cgmath::{vec2, vec3, Vector3};
+
+    use super::{Format, Scene, SceneError, Shape, MAX_SUBDIVISIONS};
+    use crate::{
+        geometry::{decomposition::TriangleMesh, heightfield::Heightfield, Polytope},
+        solver::{Method, SolverConfig},
+        world::World,
+    };
+
+    #[test]
+    fn demo_survives_a_round_trip_through_json() {
+        let scene = Scene::parse(include_str!("../scenes/demo.ron"), Format::Ron).unwrap();
+        let json = scene.to_string(Format::Json);
+        let world = Scene::parse(&json, Format::Json)
+            .unwrap()
+            .build(Path::new("."))
+            .unwrap();
+
+        assert_eq!(world.hash(), World::new().hash());
+    }
+
+    #[test]
+    fn obj_faces_are_triangulated() {
+        let source = "\
+# A unit square split into two triangles, and a quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+f 1/1 2/1 3/1
+f -4 -3 -2 -1
+";
+        let mesh = TriangleMesh::from_obj(source).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.triangles, [[0, 1, 2], [0, 1, 2], [0, 2, 3]]);
+        assert!(TriangleMesh::from_obj("v 0 0 0\nf 1 2 3").is_err());
+    }
+
+    #[test]
+    fn only_convex_polytopes_are_accepted() {
+        let cube = Polytope::new_box(vec3(1.0, 1.0, 1.0));
+        let polytope = |vertices: Vec<Vector3<f64>>, faces: Vec<Vec<usize>>| {
+            Shape::Polytope { vertices, faces }.compound(Path::new("."))
+        };
+        assert!(polytope(cube.vertices.clone(), cube.faces.clone()).is_ok());
+
+        // Pushing a vertex outwards along a diagonal bends its three faces.
+        let mut dented = cube.vertices.clone();
+        dented[0] *= 1.5;
+        assert!(polytope(dented, cube.faces.clone()).is_err());
+
+        // Faces which stay planar but leave a vertex outside.
+        let mut vertices = cube.vertices.clone();
+        vertices.push(vec3(0.0, 0.0, 2.0));
+        assert!(polytope(vertices, cube.faces.clone()).is_err());
+
+        let mut faces = cube.faces.clone();
+        faces[1] = vec![0, 1];
+        assert!(polytope(cube.vertices.clone(), faces).is_err());
+
+        let mut faces = cube.faces.clone();
+        faces[2][0] = 8;
+        assert!(polytope(cube.vertices.clone(), faces).is_err());
+    }
+
+    #[test]
+    fn invalid_solver_configs_are_rejected() {
+        let defaults = SolverConfig::default();
+        for config in [
+            defaults.substeps(0),
+            defaults.position_iterations(0),
+            defaults.velocity_iterations(0),
+            defaults.method(Method::Jacobi { relaxation: 0.0 }),
+            defaults.method(Method::Jacobi { relaxation: 2.5 }),
+            defaults.sleep_time(f64::NAN),
+        ] {
+            let scene = Scene {
+                config,
+                ..Default::default()
+            };
+            assert!(matches!(
+                scene.build(Path::new(".")),
+                Err(SceneError::InvalidConfig(_))
+            ));
+        }
+
+        let scene = Scene {
+            config: defaults.method(Method::Jacobi { relaxation: 2.0 }),
+            ..Default::default()
+        };
+        assert!(scene.build(Path::new(".")).is_ok());
+    }
+
+    #[test]
+    fn round_shapes_take_the_generator_parameters() {
+        let directory = Path::new(".");
+        for (source, expected) in [
+            (
+                "Cylinder(segments: 12, radius: 0.5, half_height: 1.0)",
+                Polytope::new_cylinder(12, 0.5, 1.0),
+            ),
+            (
+                "Cone(segments: 12, radius: 0.5, half_height: 1.0)",
+                Polytope::new_cone(12, 0.5, 1.0),
+            ),
+        ] {
+            let shape: Shape = ron::from_str(source).unwrap();
+            let compound = shape.compound(directory).unwrap();
+            assert_eq!(compound.parts[0].1.vertices, expected.vertices);
+        }
+    }
+
+    #[test]
+    fn excessive_subdivisions_are_rejected() {
+        let sphere = |subdivisions| Shape::GeodesicSphere { subdivisions }.compound(Path::new("."));
+        assert!(sphere(2).is_ok());
+        assert!(sphere(MAX_SUBDIVISIONS + 1).is_err());
+    }
+
+    #[test]
+    fn malformed_terrain_is_rejected() {
+        let scene = Scene {
+            terrain: Some(Heightfield {
+                origin: vec2(0.0, 0.0),
+                spacing: 1.0,
+                columns: 3,
+                heights: vec![0.0; 7],
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            scene.build(Path::new(".")),
+            Err(SceneError::InvalidTerrain(_))
+        ));
+    }
+}
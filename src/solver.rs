@@ -1,27 +1,744 @@
-use crate::{collision::ground, constraint::Constraint, geometry::Polytope, rigid::Rigid};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::AddAssign,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-pub fn step(rigid: &mut Rigid, polytope: &Polytope, dt: f64, substep_count: usize) {
-    let dt = dt / substep_count as f64;
+use cgmath::{InnerSpace, Vector3, Zero};
+use derive_setters::Setters;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-    for _ in 0..substep_count {
-        let past_position = rigid.position;
-        let past_rotation = rigid.rotation;
-        let past_frame = rigid.frame();
-        rigid.integrate(dt);
+use crate::{
+    collision::{ground, penetrations, sweep_and_prune, terrain},
+    constraint::Constraint,
+    frame::Frame,
+    geometry::{compound::Compound, heightfield::Heightfield, Aabb},
+    island::{self, islands},
+    rigid::Rigid,
+    world::World,
+};
 
-        let constraints = ground(rigid, past_frame, polytope);
-        solve(rigid, constraints, dt);
+/// Distance in `m` by which bounding boxes are enlarged beyond the expected motion of a step.
+const MARGIN: f64 = 0.05;
 
-        rigid.derive(past_position, past_rotation, dt);
+/// Colors with fewer constraints are not worth distributing across threads.
+const PARALLEL_BATCH: usize = 64;
+
+/// How position constraints are iterated.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Method {
+    /// Constraints are solved one after another, each building upon the corrections of the previous ones.
+    /// Independent constraints are still solved in parallel.
+    GaussSeidel,
+
+    /// All constraints are solved from the same state, and their corrections are applied together.
+    /// Each correction is divided by the number of constraints sharing the rigid and scaled by the relaxation.
+    /// Converges slower, but does not depend on the order of constraints.
+    Jacobi { relaxation: f64 },
+}
+
+#[derive(Debug, Clone, Copy, Setters, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverConfig {
+    /// Number of substeps per step.
+    pub substeps: usize,
+
+    /// Number of passes over the position constraints per substep.
+    pub position_iterations: usize,
+
+    /// Number of passes over the contact velocities per substep, which handle restitution.
+    pub velocity_iterations: usize,
+
+    pub method: Method,
+
+    /// Normal speed in `m s^-1` below which contacts do not bounce.
+    /// Avoids jitter of resting contacts.
+    pub restitution_threshold: f64,
+
+    /// Kinetic energy per mass in `J kg^-1` below which rigids are considered at rest,
+    /// unless they have their own threshold.
+    pub sleep_energy: f64,
+
+    /// How long all rigids of an island need to rest before the island falls asleep, in `s`.
+    pub sleep_time: f64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            substeps: 25,
+            position_iterations: 1,
+            velocity_iterations: 1,
+            method: Method::GaussSeidel,
+            restitution_threshold: 0.2,
+            sleep_energy: 1e-3,
+            sleep_time: 0.5,
+        }
+    }
+}
+
+impl SolverConfig {
+    /// Checks that the solver makes progress and that all thresholds are meaningful.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.substeps == 0 {
+            return Err("There must be at least one substep".to_string());
+        }
+        if self.position_iterations == 0 || self.velocity_iterations == 0 {
+            return Err(
+                "There must be at least one position and one velocity iteration".to_string(),
+            );
+        }
+        if let Method::Jacobi { relaxation } = self.method {
+            if !(relaxation > 0.0 && relaxation <= 2.0) {
+                return Err(format!("Relaxation must lie in (0, 2], got {relaxation}"));
+            }
+        }
+        for (name, value) in [
+            ("restitution threshold", self.restitution_threshold),
+            ("sleep energy", self.sleep_energy),
+            ("sleep time", self.sleep_time),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(format!(
+                    "The {name} must be non-negative and finite, got {value}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Time spent in the phases of a step.
+/// Islands are simulated in parallel and the phases within them are summed over all islands,
+/// so that together they may exceed the total.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timings {
+    /// Bounding boxes, sweep and prune, and islands.
+    pub broadphase: Duration,
+
+    /// Explicit integration of the rigids.
+    pub integrate: Duration,
+
+    /// Contacts with the ground, the terrain and between candidate pairs.
+    pub narrowphase: Duration,
+
+    /// Iterations over the position constraints.
+    pub solve: Duration,
+
+    /// Deriving velocities from positions and restitution.
+    pub velocities: Duration,
+
+    /// Rest timers and putting islands to sleep.
+    pub sleep: Duration,
+
+    /// Wall clock time of the whole step.
+    pub total: Duration,
+}
+
+impl AddAssign for Timings {
+    fn add_assign(&mut self, other: Timings) {
+        self.broadphase += other.broadphase;
+        self.integrate += other.integrate;
+        self.narrowphase += other.narrowphase;
+        self.solve += other.solve;
+        self.velocities += other.velocities;
+        self.sleep += other.sleep;
+        self.total += other.total;
+    }
+}
+
+/// Advances the world by one step of `dt`, divided into substeps.
+/// Candidate pairs for collisions are found once per step from bounding boxes covering the motion of the whole step.
+/// Rigids which cannot interact during the step form separate islands, which are simulated in parallel.
+/// Sleeping rigids are skipped entirely, unless they get touched by an awake one.
+pub fn step(world: &mut World, dt: f64) {
+    let start = Instant::now();
+    let mut timings = Timings::default();
+
+    for rigid in world.rigids.iter_mut().filter(|rigid| !rigid.asleep) {
+        rigid.apply_accumulated_impulses();
     }
+
+    let boxes: Vec<Aabb> = world
+        .rigids
+        .iter()
+        .map(|rigid| bounds(rigid, &world.shapes[rigid.shape], world.gravity, dt))
+        .collect();
+    let pairs = sweep_and_prune(&boxes);
+
+    // Sleeping islands are woken as a whole, so they must not be split up.
+    let mut sleeping: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut edges = pairs.clone();
+    edges.extend(
+        world
+            .joints
+            .iter()
+            .filter_map(|joint| Some((joint.rigids.0, joint.rigids.1?))),
+    );
+    for (i, rigid) in world.rigids.iter().enumerate() {
+        if rigid.asleep {
+            edges.push((*sleeping.entry(rigid.island).or_insert(i), i));
+        }
+    }
+    let groups = islands(world.rigids.len(), edges);
+    timings.broadphase = start.elapsed();
+
+    let simulated: Vec<(Vec<Rigid>, Vec<Constraint>, Timings)> = groups
+        .par_iter()
+        .map(|group| simulate(world, group, &boxes, &pairs, dt))
+        .collect();
+
+    let mut constraints = Vec::new();
+    for (group, (rigids, group_constraints, group_timings)) in groups.iter().zip(simulated) {
+        timings += group_timings;
+        for (&i, rigid) in group.iter().zip(rigids) {
+            world.rigids[i] = rigid;
+        }
+        constraints.extend(group_constraints.into_iter().map(|mut constraint| {
+            constraint.rigids.0 = group[constraint.rigids.0];
+            constraint.rigids.1 = constraint.rigids.1.map(|rigid| group[rigid]);
+            constraint
+        }));
+    }
+
+    let sleep = Instant::now();
+    update_sleep(world, &constraints, dt);
+    timings.sleep = sleep.elapsed();
+    constraints.retain(|constraint| constraint.normal.is_some());
+    world.contacts = constraints;
+
+    for rigid in &mut world.rigids {
+        rigid.clear_accumulators();
+    }
+    timings.total = start.elapsed();
+    world.timings = timings;
+}
+
+/// Runs all substeps on a group of rigids which only interact among themselves.
+/// Returns the rigids in the order of the group, along with the constraints of the last substep,
+/// both indexing into the group, and the time spent in each phase.
+fn simulate(
+    world: &World,
+    group: &[usize],
+    boxes: &[Aabb],
+    pairs: &[(usize, usize)],
+    dt: f64,
+) -> (Vec<Rigid>, Vec<Constraint>, Timings) {
+    let config = &world.config;
+    let h = dt / config.substeps as f64;
+    let mut timings = Timings::default();
+    let heightfield = world
+        .terrain
+        .as_deref()
+        .map(|heightfield| (heightfield, heightfield.bounds()));
+
+    let local: BTreeMap<usize, usize> = group.iter().enumerate().map(|(i, &j)| (j, i)).collect();
+    let mut rigids: Vec<Rigid> = group.iter().map(|&i| world.rigids[i]).collect();
+    let boxes: Vec<Aabb> = group.iter().map(|&i| boxes[i]).collect();
+    let pairs: Vec<(usize, usize)> = pairs
+        .iter()
+        .filter_map(|(a, b)| Some((*local.get(a)?, *local.get(b)?)))
+        .collect();
+    let joints: Vec<Constraint> = world
+        .joints
+        .iter()
+        .filter_map(|joint| {
+            let mut joint = *joint;
+            joint.rigids.0 = *local.get(&joint.rigids.0)?;
+            joint.rigids.1 = match joint.rigids.1 {
+                Some(rigid) => Some(*local.get(&rigid)?),
+                None => None,
+            };
+            Some(joint)
+        })
+        .collect();
+
+    let mut constraints = Vec::new();
+    for _ in 0..config.substeps {
+        let start = Instant::now();
+        let past = rigids.clone();
+        let past_frames: Vec<_> = past.iter().map(Rigid::frame).collect();
+
+        for rigid in rigids.iter_mut().filter(|rigid| !rigid.asleep) {
+            rigid.integrate(h, world.gravity);
+        }
+        let integrated = Instant::now();
+        timings.integrate += integrated - start;
+
+        constraints = contacts(
+            &rigids,
+            &world.shapes,
+            heightfield,
+            &boxes,
+            &pairs,
+            &past_frames,
+        );
+        constraints.extend(joints.iter().copied().filter(|joint| {
+            !rigids[joint.rigids.0].asleep
+                || joint.rigids.1.is_some_and(|rigid| !rigids[rigid].asleep)
+        }));
+
+        // Awake rigids touching sleeping ones wake their whole island.
+        for constraint in &constraints {
+            if let (a, Some(b)) = constraint.rigids {
+                if rigids[a].asleep != rigids[b].asleep {
+                    island::wake(&mut rigids, a);
+                    island::wake(&mut rigids, b);
+                }
+            }
+        }
+
+        let solving = Instant::now();
+        timings.narrowphase += solving - integrated;
+        solve(&mut rigids, &constraints, h, config);
+
+        let deriving = Instant::now();
+        timings.solve += deriving - solving;
+        for (rigid, past) in rigids.iter_mut().zip(&past) {
+            if !rigid.asleep {
+                rigid.derive(past.position, past.rotation, h);
+            }
+        }
+
+        for _ in 0..config.velocity_iterations {
+            restitute(&mut rigids, &past, &constraints, config);
+        }
+        for rigid in rigids.iter_mut().filter(|rigid| !rigid.asleep) {
+            rigid.limit_speeds();
+        }
+        timings.velocities += deriving.elapsed();
+    }
+
+    (rigids, constraints, timings)
 }
 
-pub fn solve(rigid: &mut Rigid, constraints: Vec<Constraint>, dt: f64) {
-    let compliance = 1e-6 / (dt * dt);
+/// Solves the position constraints with the configured method and number of iterations.
+/// Compliant constraints accumulate their Lagrange multipliers across iterations,
+/// so that more iterations converge towards the same stiffness instead of stiffening them.
+pub fn solve(rigids: &mut [Rigid], constraints: &[Constraint], dt: f64, config: &SolverConfig) {
+    let mut lagrange = vec![0.0; constraints.len()];
+
+    match config.method {
+        Method::GaussSeidel => {
+            // Constraints within a color do not share any rigid, so their corrections are independent.
+            // The outcome does not depend on the number of threads.
+            let colors = colors(constraints, rigids.len());
+            for _ in 0..config.position_iterations {
+                for batch in &colors {
+                    let corrections: Vec<_> = batch
+                        .par_iter()
+                        .with_min_len(PARALLEL_BATCH)
+                        .map(|&i| correction(rigids, &constraints[i], lagrange[i], dt))
+                        .collect();
+
+                    for (&i, correction) in batch.iter().zip(corrections) {
+                        if let Some((delta, direction)) = correction {
+                            lagrange[i] += delta;
+                            constraints[i].act(rigids, delta * direction);
+                        }
+                    }
+                }
+            }
+        }
+
+        Method::Jacobi { relaxation } => {
+            let mut counts = vec![0; rigids.len()];
+            for constraint in constraints {
+                counts[constraint.rigids.0] += 1;
+                if let Some(rigid) = constraint.rigids.1 {
+                    counts[rigid] += 1;
+                }
+            }
+
+            for _ in 0..config.position_iterations {
+                let corrections: Vec<_> = constraints
+                    .par_iter()
+                    .with_min_len(PARALLEL_BATCH)
+                    .zip(&lagrange)
+                    .map(|(constraint, &lagrange)| correction(rigids, constraint, lagrange, dt))
+                    .collect();
+
+                for (i, correction) in corrections.into_iter().enumerate() {
+                    if let Some((delta, direction)) = correction {
+                        let (a, b) = constraints[i].rigids;
+                        let count = b.map_or(counts[a], |b| counts[a].max(counts[b]));
+                        let delta = relaxation * delta / count as f64;
+                        lagrange[i] += delta;
+                        constraints[i].act(rigids, delta * direction);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The change of the Lagrange multiplier which moves the attachment points of a constraint
+/// to their intended distance, along with the direction of the correction.
+fn correction(
+    rigids: &[Rigid],
+    constraint: &Constraint,
+    lagrange: f64,
+    dt: f64,
+) -> Option<(f64, Vector3<f64>)> {
+    let (first, second) = constraint.points(rigids);
+    let difference = second - first;
+    let current_distance = difference.magnitude();
+    if current_distance == 0.0 {
+        return None;
+    }
+
+    let direction = difference / current_distance;
+    let compliance = constraint.compliance / (dt * dt);
+    let delta = (current_distance - constraint.distance - compliance * lagrange)
+        / (constraint.inverse_resitance(rigids, direction) + compliance);
+    Some((delta, direction))
+}
+
+/// Corrects the normal velocities at contacts after they have been derived from the positions.
+/// Contacts which approached faster than the restitution threshold before the substep bounce back,
+/// the others come to rest, which removes the spurious separating velocity of the position correction.
+fn restitute(
+    rigids: &mut [Rigid],
+    past: &[Rigid],
+    constraints: &[Constraint],
+    config: &SolverConfig,
+) {
+    for constraint in constraints {
+        let normal = match constraint.normal {
+            Some(normal) => normal,
+            None => continue,
+        };
+
+        let approach = constraint.relative_velocity(past).dot(normal);
+        let restitution = if -approach > config.restitution_threshold {
+            match constraint.rigids {
+                (a, Some(b)) => 0.5 * (rigids[a].restitution + rigids[b].restitution),
+                (a, None) => rigids[a].restitution,
+            }
+        } else {
+            0.0
+        };
+
+        let speed = constraint.relative_velocity(rigids).dot(normal);
+        let target = (-restitution * approach).max(0.0);
+        let impulse = (target - speed) / constraint.inverse_resitance(rigids, normal);
+        constraint.act_on_velocities(rigids, impulse * normal);
+    }
+}
+
+/// Greedily colors the constraint graph, such that no two constraints of the same color share a rigid.
+/// Returns the constraint indices per color, in their original order.
+fn colors(constraints: &[Constraint], rigid_count: usize) -> Vec<Vec<usize>> {
+    let mut used: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); rigid_count];
+    let mut colors: Vec<Vec<usize>> = Vec::new();
+
+    for (i, constraint) in constraints.iter().enumerate() {
+        let (a, b) = constraint.rigids;
+        let color = (0..)
+            .find(|color| !used[a].contains(color) && b.is_none_or(|b| !used[b].contains(color)))
+            .unwrap();
+
+        used[a].insert(color);
+        if let Some(b) = b {
+            used[b].insert(color);
+        }
+        if color == colors.len() {
+            colors.push(Vec::new());
+        }
+        colors[color].push(i);
+    }
+
+    colors
+}
+
+/// Contacts of awake rigids with the ground, the terrain given along with its bounds,
+/// and with the rigids of candidate pairs.
+/// Pairs of sleeping rigids are skipped, but become relevant again once one of them is woken up.
+fn contacts(
+    rigids: &[Rigid],
+    shapes: &[Arc<Compound>],
+    heightfield: Option<(&Heightfield, Aabb)>,
+    boxes: &[Aabb],
+    pairs: &[(usize, usize)],
+    past_frames: &[Frame],
+) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+
+    for (i, rigid) in rigids.iter().enumerate() {
+        if !rigid.asleep && boxes[i].min.z <= 0.0 {
+            constraints.extend(ground(i, rigid, past_frames[i], &shapes[rigid.shape]));
+        }
+        if let Some((heightfield, bounds)) = heightfield {
+            if !rigid.asleep && boxes[i].overlaps(&bounds) {
+                constraints.extend(terrain(
+                    i,
+                    rigid,
+                    past_frames[i],
+                    &shapes[rigid.shape],
+                    heightfield,
+                ));
+            }
+        }
+    }
+
+    for &(a, b) in pairs {
+        if rigids[a].asleep && rigids[b].asleep {
+            continue;
+        }
+        let frames = (rigids[a].frame(), rigids[b].frame());
+        let past = (past_frames[a], past_frames[b]);
+        let shapes = (&*shapes[rigids[a].shape], &*shapes[rigids[b].shape]);
+        constraints.extend(penetrations((a, b), frames, past, shapes));
+    }
+
+    constraints
+}
+
+/// Bounds the space a rigid may sweep through during a step of `dt`.
+fn bounds(rigid: &Rigid, shape: &Compound, gravity: Vector3<f64>, dt: f64) -> Aabb {
+    let frame = rigid.frame();
+    let center = rigid.world_center_of_mass();
+    let radius = shape
+        .vertices()
+        .map(|vertex| (frame * vertex - center).magnitude())
+        .fold(0.0, f64::max);
+
+    let mut motion = 0.0;
+    if !rigid.asleep {
+        let force = rigid.external_force + rigid.internal_force + rigid.accumulated_force;
+        let acceleration = rigid.gravity_scale.abs() * gravity.magnitude()
+            + force.magnitude() * rigid.inverse_mass;
+        motion = dt * (rigid.velocity.magnitude() + radius * rigid.angular_velocity.magnitude())
+            + dt * dt * acceleration;
+    }
+
+    Aabb::from_points(shape.vertices().map(|vertex| frame * vertex)).expand(motion + MARGIN)
+}
+
+/// Advances the rest timers and puts islands to sleep in which all rigids have rested long enough.
+/// Islands are the connected components of the last substep's constraints.
+/// Rigids which were already sleeping together stay in one island.
+fn update_sleep(world: &mut World, constraints: &[Constraint], dt: f64) {
+    let config = world.config;
+    for rigid in world.rigids.iter_mut().filter(|rigid| !rigid.asleep) {
+        let threshold = rigid.sleep_threshold.unwrap_or(config.sleep_energy);
+        if rigid.kinetic_energy() * rigid.inverse_mass < threshold {
+            rigid.sleep_time += dt;
+        } else {
+            rigid.sleep_time = 0.0;
+        }
+    }
+
+    let mut sleeping: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut edges: Vec<(usize, usize)> = constraints
+        .iter()
+        .filter_map(|constraint| Some((constraint.rigids.0, constraint.rigids.1?)))
+        .collect();
+    for (i, rigid) in world.rigids.iter().enumerate() {
+        if rigid.asleep {
+            edges.push((*sleeping.entry(rigid.island).or_insert(i), i));
+        }
+    }
+
+    for (index, island) in islands(world.rigids.len(), edges).into_iter().enumerate() {
+        let rests = island.iter().all(|&i| {
+            let rigid = &world.rigids[i];
+            rigid.asleep || rigid.sleep_time >= config.sleep_time
+        });
+
+        for i in island {
+            let rigid = &mut world.rigids[i];
+            rigid.island = index;
+            if rests && !rigid.asleep {
+                rigid.asleep = true;
+                rigid.velocity = Vector3::zero();
+                rigid.angular_velocity = Vector3::zero();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Vector3, Zero};
+
+    use super::{step, Method};
+    use crate::{geometry::Polytope, rigid::Rigid, world::World};
+
+    /// Spins a box about the principal axis with the given rank of its moment of inertia,
+    /// slightly perturbed about the other axes.
+    /// Returns the lowest angular velocity about that axis over the course of ten seconds,
+    /// relative to the initial spin.
+    fn spin(rank: usize) -> f64 {
+        let mut world = World {
+            gravity: Vector3::zero(),
+            ..Default::default()
+        };
+        let shape = world.add_shape(Polytope::new_cube().scaled(vec3(3.0, 2.0, 1.0)).into());
+        let mut rigid = Rigid::new(world.shapes[shape].rigid_metrics(1.0))
+            .unwrap()
+            .shape(shape);
+        rigid.position.z = 100.0;
+
+        let mut axes = [0, 1, 2];
+        axes.sort_by(|&a, &b| rigid.inverse_inertia[b].total_cmp(&rigid.inverse_inertia[a]));
+        let axis = axes[rank];
+
+        let spin = 5.0;
+        let mut angular_velocity = Vector3::new(0.01, 0.01, 0.01);
+        angular_velocity[axis] = spin;
+        rigid.angular_velocity = rigid.rotation * rigid.principal_rotation * angular_velocity;
+        world.add_rigid(rigid);
+
+        let mut lowest = f64::MAX;
+        for _ in 0..600 {
+            step(&mut world, 1.0 / 60.0);
+            let rigid = &world.rigids[0];
+            let angular_velocity =
+                (rigid.rotation * rigid.principal_rotation).conjugate() * rigid.angular_velocity;
+            lowest = lowest.min(angular_velocity[axis] / spin);
+        }
+        lowest
+    }
+
+    #[test]
+    fn intermediate_axis_flips() {
+        assert!(spin(1) < -0.9);
+    }
+
+    #[test]
+    fn minor_and_major_axes_are_stable() {
+        assert!(spin(0) > 0.9);
+        assert!(spin(2) > 0.9);
+    }
+
+    #[test]
+    fn settled_pile_sleeps_until_pushed() {
+        let mut world = World::default();
+        let cube = world.add_shape(Polytope::new_cube().into());
+        for i in 0..3 {
+            let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+                .unwrap()
+                .shape(cube);
+            rigid.position.z = 1.01 * i as f64;
+            world.add_rigid(rigid);
+        }
+
+        for _ in 0..120 {
+            step(&mut world, 1.0 / 60.0);
+        }
+        assert!(world.rigids.iter().all(|rigid| rigid.asleep));
+        assert!(world
+            .rigids
+            .iter()
+            .all(|rigid| rigid.island == world.rigids[0].island));
+        assert!((world.rigids[2].position.z - 2.0).abs() < 1e-3);
+
+        let positions: Vec<_> = world.rigids.iter().map(|rigid| rigid.position).collect();
+        step(&mut world, 1.0 / 60.0);
+        assert!(world
+            .rigids
+            .iter()
+            .map(|rigid| rigid.position)
+            .eq(positions));
+
+        let top = world.rigids[2].world_center_of_mass();
+        world.rigids[2].add_impulse_at_point(vec3(0.5, 0.0, 0.0), top);
+        step(&mut world, 1.0 / 60.0);
+        assert!(world.rigids.iter().all(|rigid| !rigid.asleep));
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        let simulate = |threads: usize| {
+            let mut world = World::default();
+            world.config.substeps = 10;
+            let cube = world.add_shape(Polytope::new_cube().into());
+            for i in 0..24 {
+                let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+                    .unwrap()
+                    .shape(cube);
+                rigid.position = vec3(
+                    (i % 4) as f64 * 1.1,
+                    (i / 4 % 2) as f64 * 2.5,
+                    1.5 * (i / 8) as f64,
+                );
+                rigid.angular_velocity = vec3(0.1 * i as f64, -0.2, 0.3);
+                world.add_rigid(rigid);
+            }
+
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                for _ in 0..60 {
+                    step(&mut world, 1.0 / 60.0);
+                }
+            });
+            world
+                .rigids
+                .iter()
+                .map(|rigid| (rigid.position, rigid.rotation))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(simulate(1), simulate(4));
+    }
+
+    /// Drops a cube from a height of two meters and returns the peak height after the first bounce.
+    /// Several velocity iterations are needed, since the four corners touch down at once.
+    fn bounce(restitution: f64) -> f64 {
+        let mut world = World::default();
+        world.config.velocity_iterations = 4;
+        let cube = world.add_shape(Polytope::new_cube().into());
+        let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+            .unwrap()
+            .shape(cube)
+            .restitution(restitution);
+        rigid.position.z = 2.0;
+        world.add_rigid(rigid);
+
+        let mut bounced = false;
+        let mut peak = 0.0;
+        for _ in 0..180 {
+            step(&mut world, 1.0 / 60.0);
+            let rigid = &world.rigids[0];
+            bounced |= rigid.velocity.z > 0.0 && rigid.position.z < 0.1;
+            if bounced {
+                peak = rigid.position.z.max(peak);
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn restitution_scales_bounce_height() {
+        assert!(bounce(0.0) < 0.01);
+        assert!((bounce(0.5) - 0.25 * 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn jacobi_iterations_support_a_stack() {
+        let mut world = World::default();
+        world.config = world
+            .config
+            .method(Method::Jacobi { relaxation: 1.5 })
+            .position_iterations(4);
+        let cube = world.add_shape(Polytope::new_cube().into());
+        for i in 0..3 {
+            let mut rigid = Rigid::new(world.shapes[cube].rigid_metrics(1.0))
+                .unwrap()
+                .shape(cube);
+            rigid.position.z = 1.01 * i as f64;
+            world.add_rigid(rigid);
+        }
 
-    for mut constraint in constraints {
-        let difference = constraint.current_distance() - constraint.distance;
-        let lagrange_factor = difference / (constraint.inverse_resitance(&[rigid]) + compliance);
-        constraint.act(&mut [rigid], lagrange_factor);
+        for _ in 0..180 {
+            step(&mut world, 1.0 / 60.0);
+        }
+        assert!((world.rigids[2].position.z - 2.0).abs() < 1e-3);
     }
 }
@@ -1,44 +1,329 @@
-use cgmath::{Deg, Euler, Quaternion, Rotation3};
+use std::{hash::Hasher, path::Path, sync::Arc};
 
-use crate::{collision, debug, geometry, rigid, solver};
+use cgmath::{vec3, Vector3};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy)]
+use crate::{
+    constraint::Constraint,
+    debug,
+    geometry::{compound::Compound, heightfield::Heightfield},
+    island, rigid,
+    scene::{Format, Scene},
+    solver,
+};
+
+/// Rigids, shapes and joints are identified by their index and always processed in that order.
+/// Together with ordered containers throughout collision detection and solving,
+/// this makes identical worlds evolve bit-identically, regardless of the number of threads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct World {
-    pub a: rigid::Rigid,
-    pub b: rigid::Rigid,
+    pub rigids: Vec<rigid::Rigid>,
+
+    /// Shapes referenced by the rigids.
+    /// They are shared, so that cloning the world does not copy the geometry.
+    pub shapes: Vec<Arc<Compound>>,
+
+    /// Persistent constraints between rigids, or between a rigid and the world.
+    pub joints: Vec<Constraint>,
+
+    /// Terrain which rigids collide with in addition to the ground plane.
+    /// Like the shapes, it is shared between clones of the world.
+    #[serde(default)]
+    pub terrain: Option<Arc<Heightfield>>,
+
+    /// Acceleration acting on all rigid bodies, scaled by their individual gravity scale.
+    /// Measured in `m s^-2`.
+    pub gravity: Vector3<f64>,
+
+    pub config: solver::SolverConfig,
+
+    /// Contacts of the last substep, for inspection.
+    /// They are generated anew every substep, so they are not part of snapshots.
+    #[serde(skip)]
+    pub contacts: Vec<Constraint>,
+
+    /// Time spent in the phases of the last step, for profiling.
+    #[serde(skip)]
+    pub timings: solver::Timings,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World {
+            rigids: Vec::new(),
+            shapes: Vec::new(),
+            joints: Vec::new(),
+            terrain: None,
+            gravity: vec3(0.0, 0.0, -9.81),
+            config: Default::default(),
+            contacts: Vec::new(),
+            timings: Default::default(),
+        }
+    }
 }
 
 impl World {
-    pub fn new(p1: &geometry::Polytope, p2: &geometry::Polytope) -> World {
-        let mut a = rigid::Rigid::new(p1.rigid_metrics(0.1));
-        let mut b = rigid::Rigid::new(p2.rigid_metrics(5.0));
+    /// The demo scene described by `scenes/demo.ron`.
+    pub fn new() -> World {
+        Scene::parse(include_str!("../scenes/demo.ron"), Format::Ron)
+            .and_then(|scene| scene.build(Path::new("scenes")))
+            .expect("Invalid demo scene")
+    }
+
+    /// Adds a shape which rigids can refer to by the returned index.
+    pub fn add_shape(&mut self, compound: Compound) -> usize {
+        self.shapes.push(Arc::new(compound));
+        self.shapes.len() - 1
+    }
+
+    pub fn add_rigid(&mut self, rigid: rigid::Rigid) -> usize {
+        self.rigids.push(rigid);
+        self.rigids.len() - 1
+    }
+
+    /// Adds a joint and wakes the rigids it connects.
+    pub fn add_joint(&mut self, joint: Constraint) -> usize {
+        self.wake_constrained(&joint);
+        self.joints.push(joint);
+        self.joints.len() - 1
+    }
+
+    /// Removes a joint and wakes the rigids it connected.
+    pub fn remove_joint(&mut self, index: usize) -> Constraint {
+        let joint = self.joints.remove(index);
+        self.wake_constrained(&joint);
+        joint
+    }
+
+    /// Wakes a sleeping rigid along with all other rigids of its island.
+    pub fn wake(&mut self, index: usize) {
+        island::wake(&mut self.rigids, index);
+    }
+
+    fn wake_constrained(&mut self, constraint: &Constraint) {
+        self.wake(constraint.rigids.0);
+        if let Some(rigid) = constraint.rigids.1 {
+            self.wake(rigid);
+        }
+    }
+
+    /// Total linear momentum in `kg m s^-1`.
+    pub fn momentum(&self) -> Vector3<f64> {
+        self.rigids.iter().map(rigid::Rigid::momentum).sum()
+    }
 
-        a.position.z = 4.0;
-        a.velocity.y = 2.5;
-        a.angular_velocity.x = -4.0;
-        a.angular_velocity.y = 1.0;
-        a.external_force.z = -2.0;
+    /// The linear impulse gravity, forces and accumulated impulses will exert on all rigids during the next step
+    /// in `N s`. Contact and joint impulses are not included.
+    pub fn external_impulse(&self, dt: f64) -> Vector3<f64> {
+        self.rigids
+            .iter()
+            .map(|rigid| rigid.external_impulse(dt, self.gravity))
+            .sum()
+    }
 
-        b.position.x = 4.0;
-        b.position.z = 4.0;
-        b.velocity.z = 7.0;
-        b.angular_velocity.x = -5.0;
-        b.angular_velocity.y = 5.0;
-        b.external_force.z = -2.0;
-        b.rotation = Euler::new(Deg(10.0), Deg(15.0), Deg(5.0)).into();
+    /// Total angular momentum about a world space point in `kg m^2 s^-1`.
+    pub fn angular_momentum(&self, point: Vector3<f64>) -> Vector3<f64> {
+        self.rigids
+            .iter()
+            .map(|rigid| rigid.angular_momentum(point))
+            .sum()
+    }
 
-        World { a, b }
+    /// Total translational and rotational kinetic energy in `J`.
+    pub fn kinetic_energy(&self) -> f64 {
+        self.rigids.iter().map(rigid::Rigid::kinetic_energy).sum()
+    }
+
+    /// Total gravitational potential energy in `J`, relative to the ground plane.
+    pub fn potential_energy(&self) -> f64 {
+        self.rigids
+            .iter()
+            .map(|rigid| rigid.potential_energy(self.gravity))
+            .sum()
+    }
+
+    /// Total mechanical energy in `J`, not including elastic energy stored in compliant constraints.
+    /// Without collisions, damping and external forces, it would ideally stay constant.
+    pub fn energy(&self) -> f64 {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    /// A hash of the dynamic state of all rigids, sensitive to every bit of it.
+    /// Uses FNV-1a, which unlike the standard hasher is stable across runs and platforms.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        for rigid in &self.rigids {
+            for vector in [rigid.position, rigid.velocity, rigid.angular_velocity] {
+                for value in [vector.x, vector.y, vector.z] {
+                    hasher.write_u64(value.to_bits());
+                }
+            }
+            for value in [
+                rigid.rotation.s,
+                rigid.rotation.v.x,
+                rigid.rotation.v.y,
+                rigid.rotation.v.z,
+            ] {
+                hasher.write_u64(value.to_bits());
+            }
+            hasher.write_u64(rigid.sleep_time.to_bits());
+            hasher.write_u8(rigid.asleep as u8);
+        }
+        hasher.finish()
     }
 
     #[allow(unused)]
-    pub fn integrate(
-        &mut self,
-        dt: f64,
-        p1: &geometry::Polytope,
-        p2: &geometry::Polytope,
-        debug: &mut debug::DebugLines,
-    ) {
-        solver::step(&mut self.a, p1, dt, 25);
-        solver::step(&mut self.b, p1, dt, 25);
+    pub fn integrate(&mut self, dt: f64, debug: &mut debug::DebugLines) {
+        solver::step(self, dt);
+    }
+}
+
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// Converts elapsed real time into a whole number of fixed simulation steps.
+/// The remainder is carried over, so that the simulation speed does not depend on the frame rate.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    /// Duration of a step in `s`.
+    pub dt: f64,
+
+    /// Upper bound of steps per advance, which keeps a slow simulation from falling further and further behind.
+    pub max_steps: usize,
+
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(dt: f64) -> FixedTimestep {
+        FixedTimestep {
+            dt,
+            max_steps: 16,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Accumulates elapsed time in `s` and returns the number of steps which are due.
+    pub fn advance(&mut self, elapsed: f64) -> usize {
+        self.accumulator += elapsed;
+        let steps = (self.accumulator / self.dt).floor() as usize;
+        if steps > self.max_steps {
+            self.accumulator = 0.0;
+            return self.max_steps;
+        }
+        self.accumulator -= steps as f64 * self.dt;
+        steps
+    }
+
+    /// The fraction of a step which has accumulated since the last one.
+    /// Rendering interpolates between the last two states by this amount.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, Deg, Euler};
+
+    use super::{FixedTimestep, World};
+    use crate::{
+        debug::DebugLines,
+        frame::Frame,
+        geometry::{compound::Compound, Polytope},
+        rigid::Rigid,
+    };
+
+    /// The demo scene along with a tumbling pile of boxes, tetrahedra and a concave compound.
+    fn scene() -> World {
+        let mut world = World::new();
+        let shapes = [
+            world.add_shape(Polytope::new_box(vec3(0.5, 0.3, 0.2)).into()),
+            world.add_shape(Polytope::new_tetrahedron().into()),
+            world.add_shape(Compound::new(vec![
+                (Default::default(), Polytope::new_cube()),
+                (
+                    Frame::default().position(vec3(1.0, 0.0, 0.0)),
+                    Polytope::new_cube(),
+                ),
+                (
+                    Frame::default().position(vec3(0.0, 0.0, 1.0)),
+                    Polytope::new_cube(),
+                ),
+            ])),
+        ];
+        for i in 0..12 {
+            let shape = shapes[i % shapes.len()];
+            let mut rigid = Rigid::new(world.shapes[shape].rigid_metrics(1.0))
+                .unwrap()
+                .shape(shape);
+            rigid.position = vec3(
+                0.4 * (i % 3) as f64,
+                0.3 * (i % 2) as f64,
+                1.0 + 1.3 * i as f64,
+            );
+            rigid.rotation =
+                Euler::new(Deg(7.0 * i as f64), Deg(11.0), Deg(-5.0 * i as f64)).into();
+            rigid.angular_velocity = vec3(0.5, -0.25 * i as f64, 1.0);
+            world.add_rigid(rigid);
+        }
+        world
+    }
+
+    /// Runs the scene and returns the hash after every step.
+    fn hashes(mut world: World, steps: usize) -> Vec<u64> {
+        (0..steps)
+            .map(|_| {
+                world.integrate(1.0 / 60.0, &mut DebugLines::default());
+                world.hash()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_runs_are_bitwise_identical() {
+        let first = hashes(scene(), 240);
+        let second = hashes(scene(), 240);
+
+        for (step, (first, second)) in first.iter().zip(&second).enumerate() {
+            assert_eq!(first, second, "Runs diverged at step {step}");
+        }
+    }
+
+    #[test]
+    fn hash_detects_the_smallest_change() {
+        let world = scene();
+        let mut perturbed = world.clone();
+        perturbed.rigids[5].velocity.x =
+            f64::from_bits(perturbed.rigids[5].velocity.x.to_bits() + 1);
+
+        assert_ne!(world.hash(), perturbed.hash());
+    }
+
+    #[test]
+    fn fixed_timestep_carries_over_the_remainder() {
+        let mut timestep = FixedTimestep::new(1.0 / 64.0);
+        let steps: Vec<usize> = (0..4).map(|_| timestep.advance(1.5 / 64.0)).collect();
+
+        assert_eq!(steps, [1, 2, 1, 2]);
+        assert_eq!(timestep.alpha(), 0.0);
     }
 }